@@ -0,0 +1,214 @@
+use hashbrown::HashMap;
+use itertools::Itertools;
+use num::traits::{WrappingAdd, WrappingSub};
+use num::{CheckedAdd, CheckedSub, Integer};
+use std::hash::Hash;
+
+// A sum that can run past `T`'s range without overflowing: its true value is `value` plus `wraps`
+// times the size of `T`'s range. Comparing `wraps` first compares the true values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Sum<T> {
+    wraps: i64,
+    value: T,
+}
+
+impl<T> Sum<T>
+where
+    T: Integer + CheckedAdd + CheckedSub + WrappingAdd + WrappingSub + Copy,
+{
+    fn new(value: T) -> Sum<T> {
+        Sum { wraps: 0, value }
+    }
+
+    fn add(self, other: T) -> Sum<T> {
+        match self.value.checked_add(&other) {
+            Some(value) => Sum { value, ..self },
+            None => Sum {
+                wraps: self.wraps + if other > T::zero() { 1 } else { -1 },
+                value: self.value.wrapping_add(&other),
+            },
+        }
+    }
+
+    fn sub(self, other: T) -> Sum<T> {
+        match self.value.checked_sub(&other) {
+            Some(value) => Sum { value, ..self },
+            None => Sum {
+                wraps: self.wraps + if other > T::zero() { -1 } else { 1 },
+                value: self.value.wrapping_sub(&other),
+            },
+        }
+    }
+}
+
+// Every set of `k` different indices into `values` whose values add up to `target`. Each set is in
+// ascending order, and the sets are sorted. Two numbers are found by hashing, three by walking two
+// pointers over the sorted values, and more by meeting in the middle. Sums that run past `T`'s
+// range are still compared exactly, so they never match `target`.
+pub fn k_sum<T>(values: &[T], k: usize, target: T) -> Vec<Vec<usize>>
+where
+    T: Integer + CheckedAdd + CheckedSub + WrappingAdd + WrappingSub + Copy + Hash,
+{
+    let mut sets = match k {
+        0 if target.is_zero() => vec![vec![]],
+        0 => vec![],
+        1 => (0..values.len())
+            .filter(|&i| values[i] == target)
+            .map(|i| vec![i])
+            .collect(),
+        2 => two_sum(values, target),
+        3 => three_sum(values, target),
+        _ => meet_in_the_middle(values, k, target),
+    };
+    for set in sets.iter_mut() {
+        set.sort_unstable();
+    }
+    sets.sort_unstable();
+    sets
+}
+
+fn two_sum<T>(values: &[T], target: T) -> Vec<Vec<usize>>
+where
+    T: Integer + CheckedSub + Copy + Hash,
+{
+    let mut seen: HashMap<T, Vec<usize>> = HashMap::new();
+    let mut pairs = vec![];
+    for (j, &value) in values.iter().enumerate() {
+        if let Some(earlier) = target.checked_sub(&value).and_then(|other| seen.get(&other)) {
+            pairs.extend(earlier.iter().map(|&i| vec![i, j]));
+        }
+        seen.entry(value).or_default().push(j);
+    }
+    pairs
+}
+
+fn three_sum<T>(values: &[T], target: T) -> Vec<Vec<usize>>
+where
+    T: Integer + CheckedAdd + CheckedSub + WrappingAdd + WrappingSub + Copy,
+{
+    let order: Vec<usize> = (0..values.len()).sorted_by_key(|&i| values[i]).collect();
+    let value = |position: usize| values[order[position]];
+    let target = Sum::new(target);
+    let mut triples = vec![];
+    for first in 0..order.len() {
+        let (mut low, mut high) = (first + 1, order.len().saturating_sub(1));
+        while low < high {
+            let sum = Sum::new(value(first)).add(value(low)).add(value(high));
+            if sum < target {
+                low += 1;
+            } else if sum > target {
+                high -= 1;
+            } else if value(low) == value(high) {
+                // Everything between the pointers is the same value, so any two of them will do.
+                for (a, b) in (low..=high).tuple_combinations() {
+                    triples.push(vec![order[first], order[a], order[b]]);
+                }
+                break;
+            } else {
+                // Take every pairing of the runs of equal values at each end.
+                let low_end = (low..=high).find(|&p| value(p) != value(low)).unwrap();
+                let high_start = (low..=high).rev().find(|&p| value(p) != value(high)).unwrap() + 1;
+                for a in low..low_end {
+                    for b in high_start..=high {
+                        triples.push(vec![order[first], order[a], order[b]]);
+                    }
+                }
+                low = low_end;
+                high = high_start - 1;
+            }
+        }
+    }
+    triples
+}
+
+// Splits each set into its smallest `k / 2` indices and the rest, then looks the halves up against
+// each other by their sums.
+fn meet_in_the_middle<T>(values: &[T], k: usize, target: T) -> Vec<Vec<usize>>
+where
+    T: Integer + CheckedAdd + CheckedSub + WrappingAdd + WrappingSub + Copy + Hash,
+{
+    let mut lower_halves: HashMap<Sum<T>, Vec<Vec<usize>>> = HashMap::new();
+    for set in (0..values.len()).combinations(k / 2) {
+        let sum = set.iter().fold(Sum::new(T::zero()), |sum, &i| sum.add(values[i]));
+        lower_halves.entry(sum).or_default().push(set);
+    }
+    let mut sets = vec![];
+    for upper in (0..values.len()).combinations(k - k / 2) {
+        let needed = upper.iter().fold(Sum::new(target), |sum, &i| sum.sub(values[i]));
+        for lower in lower_halves.get(&needed).into_iter().flatten() {
+            if lower.iter().all(|&i| i < upper[0]) {
+                sets.push(lower.iter().chain(upper.iter()).copied().collect());
+            }
+        }
+    }
+    sets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(values: &[i32], k: usize, target: i32) -> Vec<Vec<usize>> {
+        (0..values.len())
+            .combinations(k)
+            .filter(|set| set.iter().map(|&i| values[i]).sum::<i32>() == target)
+            .collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let values = [1721, 979, 366, 299, 675, 1456];
+        assert_eq!(k_sum(&values, 2, 2020), vec![vec![0, 3]]);
+        assert_eq!(k_sum(&values, 3, 2020), vec![vec![1, 2, 4]]);
+        assert_eq!(k_sum(&values, 1, 366), vec![vec![2]]);
+        assert_eq!(k_sum(&values, 0, 0), vec![Vec::<usize>::new()]);
+        assert!(k_sum(&values, 7, 2020).is_empty());
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        // Plenty of repeated values, negatives and zeroes, to catch sets being missed or doubled.
+        let values = [3, -1, 4, 1, -5, 9, 2, 6, -5, 3, 5, 0, 0, 3, -2, 7, 1, 1];
+        for k in 0..=6 {
+            for target in -8..=16 {
+                assert_eq!(
+                    k_sum(&values, k, target),
+                    brute_force(&values, k, target),
+                    "k = {}, target = {}",
+                    k,
+                    target
+                );
+            }
+        }
+        let same = [2; 7];
+        assert_eq!(k_sum(&same, 3, 6).len(), 35);
+        assert_eq!(k_sum(&same, 4, 8).len(), 35);
+    }
+
+    #[test]
+    fn test_unsigned_values() {
+        let values: [u32; 5] = [10, 1, 7, 3, 9];
+        assert_eq!(k_sum(&values, 2, 10), vec![vec![1, 4], vec![2, 3]]);
+        assert_eq!(k_sum(&values, 4, 21), vec![vec![0, 1, 2, 3]]);
+        assert!(k_sum(&values, 2, 2).is_empty());
+    }
+
+    #[test]
+    fn test_sums_past_the_range() {
+        assert!(k_sum(&[u32::MAX, 1, 0], 3, 0).is_empty());
+        assert!(k_sum(&[u32::MAX, 1, 0, 2], 4, 2).is_empty());
+        assert_eq!(k_sum(&[u32::MAX, 1, 0, 2], 2, u32::MAX), vec![vec![0, 2]]);
+
+        // Halves and partial sums run past the range while whole sets land back inside it.
+        let values: [i8; 10] = [127, 127, -128, -128, 100, -100, 1, -1, 0, 90];
+        for k in 0..=5 {
+            for target in (-128..=127).step_by(3) {
+                let expected: Vec<Vec<usize>> = (0..values.len())
+                    .combinations(k)
+                    .filter(|set| set.iter().map(|&i| values[i] as i64).sum::<i64>() == target as i64)
+                    .collect();
+                assert_eq!(k_sum(&values, k, target), expected, "k = {}, target = {}", k, target);
+            }
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use num::Integer;
+pub mod k_sum;
+
+use crate::day_1::k_sum::k_sum;
 use std::fs;
 
 fn get_vals() -> Vec<i32> {
@@ -8,56 +10,32 @@ fn get_vals() -> Vec<i32> {
 
 pub fn solve_part_1() -> Result<(), ()> {
     let vals = get_vals();
-    println!("Solution: {}", part_one(&vals, 2020).expect("No solution?"));
-    Ok(())
+    print_solutions(&vals, 2)
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
     let vals = get_vals();
-    println!("Solution: {}", part_two(&vals).expect("No solution?"));
-    Ok(())
+    print_solutions(&vals, 3)
 }
 
-fn part_one(vals: &[i32], target: i32) -> Option<i32> {
-    let mut a = vals[0];
-    let mut offset = 1;
-    while offset < vals.len() {
-        for b in vals[offset..vals.len()].iter() {
-            if (a + b) == target {
-                return Some(a * b);
-            }
-        }
-        a = vals[offset];
-        offset += 1;
+fn print_solutions(vals: &[i32], k: usize) -> Result<(), ()> {
+    let solutions = solutions(vals, k, 2020);
+    if solutions.is_empty() {
+        println!("No solution?");
+        return Err(());
     }
-    None
-}
-
-fn part_two(vals: &[i32]) -> Option<i32> {
-    let mut offset = 0;
-    while offset < vals.len() - 1 {
-        let target = 2020 - vals[offset];
-        if let Some(result) = part_one(&vals[(offset + 1)..vals.len()], target) {
-            return Some(vals[offset] * result);
-        }
-        offset += 1
+    for solution in solutions {
+        println!("Solution: {}", solution);
     }
-    None
+    Ok(())
 }
 
-use itertools::Itertools;
-use std::ops::{Add, Mul};
-
-pub fn combinations_summing_to_n<'a, T: 'a + Copy + Integer + Add + Mul>(
-    vals: &'a [T],
-    take: usize,
-    target: T,
-) -> Option<T> {
-    vals.iter()
-        .copied()
-        .combinations(take)
-        .find(|combo| combo.iter().fold(T::zero(), |acc, i| acc + *i) == target)
-        .map(|i| i.iter().fold(T::one(), |acc, i| acc * *i))
+// The product of every set of `k` entries summing to `target`, in case there's more than one.
+fn solutions(vals: &[i32], k: usize, target: i32) -> Vec<i32> {
+    k_sum(vals, k, target)
+        .iter()
+        .map(|set| set.iter().map(|&i| vals[i]).product())
+        .collect()
 }
 
 #[cfg(test)]
@@ -65,23 +43,14 @@ mod tests {
     use super::*;
     const VALS: [i32; 6] = [1721, 979, 366, 299, 675, 1456];
 
-    #[test]
-    fn test_part_one_combo() {
-        assert_eq!(combinations_summing_to_n(&VALS, 2, 2020).unwrap(), 514579);
-    }
-
-    #[test]
-    fn test_part_two_combo() {
-        assert_eq!(combinations_summing_to_n(&VALS, 3, 2020).unwrap(), 241861950)
-    }
-
     #[test]
     fn test_part_one() {
-        assert_eq!(part_one(&VALS, 2020).unwrap(), 514579);
+        assert_eq!(solutions(&VALS, 2, 2020), vec![514579]);
     }
 
     #[test]
     fn test_part_two() {
-        assert_eq!(part_two(&VALS).unwrap(), 241861950);
+        assert_eq!(solutions(&VALS, 3, 2020), vec![241861950]);
+        assert_eq!(solutions(&[1010, 1010, 1010], 2, 2020), vec![1020100; 3]);
     }
 }
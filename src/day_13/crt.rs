@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use num::{BigInt, Integer, Signed, ToPrimitive};
+use std::fmt;
+
+// `x ≡ residue (mod modulus)`, with the residue kept in `0..modulus`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Congruence<T> {
+    pub residue: T,
+    pub modulus: T,
+}
+
+impl<T: Integer + Signed + Clone + fmt::Display> Congruence<T> {
+    pub fn new(residue: T, modulus: T) -> Result<Congruence<T>> {
+        if !modulus.is_positive() {
+            return Err(anyhow!("Modulus must be positive, not {}", modulus));
+        }
+        Ok(Congruence {
+            residue: residue.mod_floor(&modulus),
+            modulus,
+        })
+    }
+
+    // The congruence that holds exactly when both `self` and `other` do. The moduli don't have to
+    // be coprime, but then the residues have to agree modulo their gcd. Intermediate values are up
+    // to the square of the larger modulus, so fixed width types need room for that.
+    pub fn combine(&self, other: &Congruence<T>) -> Result<Congruence<T>> {
+        let gcd = self.modulus.extended_gcd(&other.modulus);
+        let difference = other.residue.clone() - self.residue.clone();
+        if !difference.is_multiple_of(&gcd.gcd) {
+            return Err(anyhow!("{} and {} can't both hold", self, other));
+        }
+        let step = other.modulus.clone() / gcd.gcd.clone();
+        let multiple = (difference / gcd.gcd).mod_floor(&step) * gcd.x.mod_floor(&step);
+        let lcm = self.modulus.clone() * step.clone();
+        Congruence::new(
+            self.residue.clone() + self.modulus.clone() * multiple.mod_floor(&step),
+            lcm,
+        )
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Congruence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "x ≡ {} (mod {})", self.residue, self.modulus)
+    }
+}
+
+// The single congruence equivalent to all of them, or an error naming where they first conflict.
+pub fn solve<T, I>(congruences: I) -> Result<Congruence<T>>
+where
+    T: Integer + Signed + Clone + fmt::Display,
+    I: IntoIterator<Item = Congruence<T>>,
+{
+    congruences
+        .into_iter()
+        .try_fold(Congruence::new(T::zero(), T::one())?, |solution, congruence| {
+            solution.combine(&congruence)
+        })
+}
+
+// `solve` for `(residue, modulus)` pairs too big for `i128`, going through `BigInt`. Fails if the
+// combined modulus doesn't fit in a `u128`.
+pub fn solve_u128(congruences: &[(u128, u128)]) -> Result<(u128, u128)> {
+    let congruences = congruences
+        .iter()
+        .map(|&(residue, modulus)| Congruence::new(BigInt::from(residue), BigInt::from(modulus)))
+        .collect::<Result<Vec<_>>>()?;
+    let solution = solve(congruences)?;
+    match (solution.residue.to_u128(), solution.modulus.to_u128()) {
+        (Some(residue), Some(modulus)) => Ok((residue, modulus)),
+        _ => Err(anyhow!("{} doesn't fit in a u128", solution)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn congruence(residue: i128, modulus: i128) -> Congruence<i128> {
+        Congruence::new(residue, modulus).unwrap()
+    }
+
+    #[test]
+    fn test_coprime_moduli() {
+        let solution = solve(vec![congruence(2, 3), congruence(3, 5), congruence(2, 7)]).unwrap();
+        assert_eq!(solution, congruence(23, 105));
+        assert_eq!(congruence(-1, 7), congruence(6, 7));
+        assert_eq!(solve(vec![]).unwrap(), congruence(0, 1));
+        assert!(Congruence::new(1, 0).is_err());
+        assert!(Congruence::new(1, -3).is_err());
+    }
+
+    #[test]
+    fn test_non_coprime_moduli() {
+        // Checked against every x below the lcm.
+        let systems = vec![
+            vec![(3, 4), (5, 6)],
+            vec![(1, 6), (4, 9), (7, 15)],
+            vec![(10, 12), (4, 18), (22, 30)],
+            vec![(0, 8), (0, 12), (6, 9)],
+        ];
+        for system in systems {
+            let congruences: Vec<_> = system.iter().map(|&(r, m)| congruence(r, m)).collect();
+            let solution = solve(congruences).unwrap();
+            let lcm = system.iter().fold(1, |lcm, &(_, m)| num::integer::lcm(lcm, m));
+            let expected = (0..lcm).find(|x| system.iter().all(|&(r, m)| x % m == r % m));
+            assert_eq!(solution.modulus, lcm);
+            assert_eq!(Some(solution.residue), expected);
+        }
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let error = solve(vec![congruence(1, 4), congruence(2, 6)]).unwrap_err();
+        assert_eq!(error.to_string(), "x ≡ 1 (mod 4) and x ≡ 2 (mod 6) can't both hold");
+        assert!(solve(vec![congruence(1, 2), congruence(2, 3), congruence(0, 6)]).is_err());
+    }
+
+    #[test]
+    fn test_large_moduli() {
+        let primes = [
+            18_446_744_073_709_551_557_u128,
+            18_446_744_073_709_551_533,
+            1_000_000_007,
+        ];
+        let x = 123_456_789_012_345_678_901_234_567_890_u128;
+        let system: Vec<_> = primes.iter().map(|&p| (x % p, p)).collect();
+        assert_eq!(solve_u128(&system[..2]).unwrap(), (x, primes[0] * primes[1]));
+        assert!(solve_u128(&system).is_err());
+
+        let system = system
+            .iter()
+            .map(|&(r, m)| Congruence::new(BigInt::from(r), BigInt::from(m)));
+        let solution = solve(system.collect::<Result<Vec<_>>>().unwrap()).unwrap();
+        assert_eq!(solution.residue, BigInt::from(x));
+        assert_eq!(solution.modulus, BigInt::from(primes[0]) * primes[1] * primes[2]);
+    }
+}
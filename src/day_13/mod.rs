@@ -1,3 +1,7 @@
+mod crt;
+mod schedule;
+
+use crate::day_13::schedule::Schedule;
 use std::fs;
 
 fn parse_input(input: &str) -> Problem {
@@ -41,32 +45,9 @@ fn part_1(problem: Problem) -> u64 {
     nearest_freq * waiting
 }
 
-fn get_modulo_expectation((place, value): (usize, &u64)) -> (u64, u64) {
-    let place = place as u64;
-    if *value > place {
-        ((*value - place) % *value, *value)
-    } else {
-        let mut place = place;
-        let value = *value;
-        while place > value {
-            place -= value;
-        }
-        (value - place, value)
-    }
-}
-
-fn make_constraints(buses: &[u64]) -> Vec<(u64, u64)> {
-    buses
-        .iter()
-        .enumerate()
-        .filter(|(_, n)| **n != 0)
-        .map(get_modulo_expectation)
-        .collect()
-}
-
-fn part_2(problem: Problem) -> u64 {
-    let constraints = make_constraints(&problem.available_freqs);
-    sieving_search(constraints)
+fn part_2(problem: Problem) -> u128 {
+    let schedule = Schedule::from_ids(&problem.available_freqs);
+    schedule.next_alignment(0).expect("The buses never line up")
 }
 
 struct Problem {
@@ -74,25 +55,6 @@ struct Problem {
     available_freqs: Vec<u64>,
 }
 
-fn sieving_search(mut list: Vec<(u64, u64)>) -> u64 {
-    list.sort_by_key(|(_, modulo)| *modulo);
-    let mut inverse_mod_order = list.into_iter().rev();
-    let (expected, modulo) = inverse_mod_order.next().unwrap();
-    let mut current_number = expected;
-    let mut current_increment = modulo;
-    for (expected, modulo) in inverse_mod_order {
-        let mut found = false;
-        while !found {
-            current_number += current_increment;
-            if current_number % modulo == expected {
-                found = true;
-                current_increment *= modulo;
-            }
-        }
-    }
-    current_number
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,12 +83,16 @@ mod tests {
     }
 
     #[test]
-    fn test_sieving() {
+    fn test_solving_constraints() {
         let list = vec![(0, 3), (3, 4), (4, 5)];
-        assert_eq!(sieving_search(list), 39);
+        assert_eq!(crt::solve_u128(&list).unwrap(), (39, 60));
 
         let list = vec![(0, 3), (2, 7), (6, 11), (3, 13)];
-        assert_eq!(sieving_search(list), 2382);
+        assert_eq!(crt::solve_u128(&list).unwrap(), (2382, 3003));
+
+        // Bus IDs needn't be prime, as long as the offsets agree where they share factors.
+        assert_eq!(part_2(parse_input("0\n4,x,6")), 4);
+        assert!(crt::solve_u128(&Schedule::from_ids(&[4, 6]).constraints()).is_err());
     }
 
     #[test]
@@ -149,7 +115,7 @@ mod tests {
         let expected_result = vec![(0, 7), (3, 5)];
         let problem = parse_input(input_str);
         let buses = problem.available_freqs;
-        assert_eq!(make_constraints(&buses), expected_result);
+        assert_eq!(Schedule::from_ids(&buses).constraints(), expected_result);
     }
 
     #[test]
@@ -0,0 +1,114 @@
+use crate::day_13::crt;
+use anyhow::Result;
+use num::Integer;
+
+// A bus that leaves at every multiple of `id`, and is wanted `offset` minutes after an alignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bus {
+    id: u128,
+    offset: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    buses: Vec<Bus>,
+}
+
+// The times every bus in a schedule leaves at its offset: `first`, then every `period` minutes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub first: u128,
+    pub period: u128,
+}
+
+impl Schedule {
+    // The puzzle's list of IDs, where 0 stands for an "x", each wanted as many minutes after the
+    // alignment as its place in the list.
+    pub fn from_ids(ids: &[u64]) -> Schedule {
+        let buses = ids
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| **id != 0)
+            .map(|(place, &id)| Bus {
+                id: id as u128,
+                offset: place as u128,
+            })
+            .collect();
+        Schedule { buses }
+    }
+
+    // What an alignment time has to be modulo each bus's ID, as `(residue, modulus)`.
+    pub fn constraints(&self) -> Vec<(u128, u128)> {
+        self.buses
+            .iter()
+            .map(|bus| ((bus.id - bus.offset % bus.id) % bus.id, bus.id))
+            .collect()
+    }
+
+    pub fn alignment(&self) -> Result<Alignment> {
+        let (first, period) = crt::solve_u128(&self.constraints())?;
+        Ok(Alignment { first, period })
+    }
+
+    pub fn next_alignment(&self, time: u128) -> Result<u128> {
+        Ok(self.alignment()?.next_at_or_after(time))
+    }
+}
+
+impl Alignment {
+    pub fn next_at_or_after(&self, time: u128) -> u128 {
+        if time <= self.first {
+            self.first
+        } else {
+            self.first + Integer::div_ceil(&(time - self.first), &self.period) * self.period
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Schedule {
+        Schedule::from_ids(&[7, 13, 0, 0, 59, 0, 31, 19])
+    }
+
+    fn is_aligned(schedule: &Schedule, time: u128) -> bool {
+        schedule
+            .buses
+            .iter()
+            .all(|bus| Integer::is_multiple_of(&(time + bus.offset), &bus.id))
+    }
+
+    #[test]
+    fn test_alignments() {
+        let schedule = example();
+        let alignment = schedule.alignment().unwrap();
+        assert_eq!(alignment.first, 1_068_781);
+        assert_eq!(alignment.period, 7 * 13 * 59 * 31 * 19);
+        assert_eq!(schedule.next_alignment(0).unwrap(), 1_068_781);
+        assert_eq!(schedule.next_alignment(1_068_781).unwrap(), 1_068_781);
+        let next = schedule.next_alignment(1_068_782).unwrap();
+        assert_eq!(next, 1_068_781 + alignment.period);
+        assert!((0..3).all(|i| is_aligned(&schedule, alignment.first + i * alignment.period)));
+        assert!((1_068_782..1_068_782 + 10_000).all(|time| !is_aligned(&schedule, time)));
+    }
+
+    #[test]
+    fn test_custom_offsets() {
+        let custom = Schedule {
+            buses: vec![
+                Bus { id: 4, offset: 1 },
+                Bus { id: 6, offset: 3 },
+                Bus { id: 9, offset: 99 },
+            ],
+        };
+        let alignment = custom.alignment().unwrap();
+        assert_eq!(alignment, Alignment { first: 27, period: 36 });
+        assert!(is_aligned(&custom, alignment.first));
+        assert!((0..alignment.first).all(|time| !is_aligned(&custom, time)));
+
+        let clashing = Schedule::from_ids(&[4, 6]);
+        assert!(clashing.next_alignment(0).is_err());
+    }
+}
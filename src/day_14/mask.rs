@@ -0,0 +1,123 @@
+use crate::day_14::memory::AddressPattern;
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+// A bitmask like "X1XX0X", most significant bit first, for values of up to 64 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mask {
+    width: u32,
+    ones: u64,
+    floating: u64,
+}
+
+impl Mask {
+    // The bits a value keeps under version 1: the floating ones, and nothing above the mask.
+    pub fn and_mask(&self) -> u64 {
+        self.floating
+    }
+
+    pub fn or_mask(&self) -> u64 {
+        self.ones
+    }
+
+    pub fn floating(&self) -> u64 {
+        self.floating
+    }
+
+    // Version 1: the mask's ones and zeros overwrite the value's bits.
+    pub fn apply(&self, value: u64) -> u64 {
+        (value & self.and_mask()) | self.or_mask()
+    }
+
+    // Version 2: the mask's ones are set in the address, its zeros leave it alone, and its
+    // floating bits take every value.
+    pub fn address_pattern(&self, address: u64) -> AddressPattern {
+        AddressPattern::new(address | self.or_mask(), self.floating())
+    }
+}
+
+impl FromStr for Mask {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let width = text.chars().count();
+        if width == 0 || width > 64 {
+            return Err(anyhow!("Masks must be 1 to 64 bits wide, not {}: {}", width, text));
+        }
+        let mut mask = Mask {
+            width: width as u32,
+            ones: 0,
+            floating: 0,
+        };
+        for (position, c) in text.chars().enumerate() {
+            let bit = 1 << (width - 1 - position);
+            match c {
+                '1' => mask.ones |= bit,
+                '0' => (),
+                'X' => mask.floating |= bit,
+                other => {
+                    return Err(anyhow!(
+                        "Invalid mask character {:?} at position {}: {}",
+                        other,
+                        position + 1,
+                        text
+                    ))
+                }
+            }
+        }
+        Ok(mask)
+    }
+}
+
+impl fmt::Display for Mask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for position in (0..self.width).rev() {
+            let bit = 1 << position;
+            let c = if self.floating & bit != 0 {
+                'X'
+            } else if self.ones & bit != 0 {
+                '1'
+            } else {
+                '0'
+            };
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_masks() {
+        let mask: Mask = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X".parse().unwrap();
+        assert_eq!(mask.or_mask(), 0b1000000);
+        assert_eq!(mask.and_mask(), 0xF_FFFF_FFBD);
+        assert_eq!(mask.to_string(), "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X");
+
+        let wide = format!("1{}X", "0".repeat(62));
+        let mask: Mask = wide.parse().unwrap();
+        assert_eq!((mask.or_mask(), mask.floating()), (1 << 63, 1));
+        assert_eq!(mask.to_string(), wide);
+
+        let error = |text: &str| text.parse::<Mask>().unwrap_err().to_string();
+        assert_eq!(error("10x1"), "Invalid mask character 'x' at position 3: 10x1");
+        assert!(error("").starts_with("Masks must be 1 to 64 bits wide, not 0"));
+        assert!(error(&"X".repeat(65)).starts_with("Masks must be 1 to 64 bits wide, not 65"));
+    }
+
+    #[test]
+    fn test_applying_masks() {
+        let mask: Mask = "X1XX0X".parse().unwrap();
+        assert_eq!(mask.apply(0b111111), 0b111101);
+        assert_eq!(mask.apply(0b1000000), 0b010000);
+        let pattern = mask.address_pattern(0b1000010);
+        assert!(pattern.contains(0b1010010));
+        assert!(pattern.contains(0b1111111));
+        assert!(!pattern.contains(0b0010010));
+        assert_eq!(pattern.address_count(), 16);
+    }
+}
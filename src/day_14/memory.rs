@@ -0,0 +1,174 @@
+// Every address matching `fixed` on the bits not in `floating`, whatever the floating bits are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressPattern {
+    fixed: u64,
+    floating: u64,
+}
+
+impl AddressPattern {
+    pub fn new(fixed: u64, floating: u64) -> AddressPattern {
+        AddressPattern {
+            fixed: fixed & !floating,
+            floating,
+        }
+    }
+
+    pub fn address_count(&self) -> u128 {
+        1 << self.floating.count_ones()
+    }
+
+    #[allow(dead_code)]
+    pub fn contains(&self, address: u64) -> bool {
+        (address ^ self.fixed) & !self.floating == 0
+    }
+
+    pub fn overlaps(&self, other: &AddressPattern) -> bool {
+        (self.fixed ^ other.fixed) & !self.floating & !other.floating == 0
+    }
+
+    // The addresses of `self` that aren't in `other`, as disjoint patterns: one for each bit that
+    // floats in `self` but not in `other`, fixed to the value `other` doesn't have, with the
+    // earlier such bits fixed to the value it does have.
+    pub fn without(&self, other: &AddressPattern) -> Vec<AddressPattern> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let mut pieces = vec![];
+        let mut rest = *self;
+        let mut to_split = self.floating & !other.floating;
+        while to_split != 0 {
+            let bit = to_split & to_split.wrapping_neg();
+            to_split &= !bit;
+            rest.floating &= !bit;
+            pieces.push(AddressPattern::new(rest.fixed | (!other.fixed & bit), rest.floating));
+            rest.fixed |= other.fixed & bit;
+        }
+        pieces
+    }
+}
+
+// Memory written a pattern at a time. The regions are kept disjoint by cutting each new write out
+// of the ones before it, so nothing ever expands a pattern into its addresses.
+#[derive(Debug, Default)]
+pub struct SparseMemory {
+    regions: Vec<(AddressPattern, u64)>,
+}
+
+impl SparseMemory {
+    pub fn new() -> SparseMemory {
+        SparseMemory::default()
+    }
+
+    pub fn write(&mut self, pattern: AddressPattern, value: u64) {
+        let regions = std::mem::take(&mut self.regions);
+        self.regions = regions
+            .into_iter()
+            .flat_map(|(region, old_value)| {
+                region
+                    .without(&pattern)
+                    .into_iter()
+                    .map(move |piece| (piece, old_value))
+            })
+            .collect();
+        self.regions.push((pattern, value));
+    }
+
+    #[allow(dead_code)]
+    pub fn get(&self, address: u64) -> Option<u64> {
+        self.regions
+            .iter()
+            .find(|(region, _)| region.contains(address))
+            .map(|&(_, value)| value)
+    }
+
+    pub fn sum(&self) -> u128 {
+        self.regions
+            .iter()
+            .map(|(region, value)| region.address_count() * *value as u128)
+            .sum()
+    }
+
+    // How many addresses have been written to.
+    #[allow(dead_code)]
+    pub fn address_count(&self) -> u128 {
+        self.regions.iter().map(|(region, _)| region.address_count()).sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn region_count(&self) -> usize {
+        self.regions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashbrown::HashMap;
+
+    fn addresses(pattern: &AddressPattern) -> Vec<u64> {
+        (0..64).filter(|&address| pattern.contains(address)).collect()
+    }
+
+    #[test]
+    fn test_pattern_difference() {
+        let patterns = [
+            AddressPattern::new(0b100000, 0b001011),
+            AddressPattern::new(0b000001, 0b110000),
+            AddressPattern::new(0b101010, 0),
+            AddressPattern::new(0, 0b111111),
+            AddressPattern::new(0b010100, 0b000011),
+        ];
+        for a in &patterns {
+            assert_eq!(addresses(a).len() as u128, a.address_count());
+            for b in &patterns {
+                let pieces = a.without(b);
+                let mut remaining: Vec<u64> = pieces.iter().flat_map(addresses).collect();
+                remaining.sort_unstable();
+                let expected: Vec<u64> = addresses(a)
+                    .into_iter()
+                    .filter(|&address| !b.contains(address))
+                    .collect();
+                assert_eq!(remaining, expected);
+                assert_eq!(a.overlaps(b), expected.len() < addresses(a).len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_writes_match_expanded_memory() {
+        let writes = [
+            (AddressPattern::new(0b000000, 0b110011), 7),
+            (AddressPattern::new(0b001000, 0b000110), 3),
+            (AddressPattern::new(0b101101, 0), 100),
+            (AddressPattern::new(0b010000, 0b001101), 9),
+            (AddressPattern::new(0b000010, 0b100000), 1),
+        ];
+        let mut memory = SparseMemory::new();
+        let mut expanded = HashMap::new();
+        for &(pattern, value) in &writes {
+            memory.write(pattern, value);
+            for address in addresses(&pattern) {
+                expanded.insert(address, value);
+            }
+            assert_eq!(memory.sum(), expanded.values().map(|&v| v as u128).sum());
+            assert_eq!(memory.address_count(), expanded.len() as u128);
+        }
+        for address in 0..64 {
+            assert_eq!(memory.get(address), expanded.get(&address).copied());
+        }
+    }
+
+    #[test]
+    fn test_many_floating_bits() {
+        let mut memory = SparseMemory::new();
+        memory.write(AddressPattern::new(0, (1 << 40) - 1), 2);
+        memory.write(AddressPattern::new(1 << 39, (1 << 39) - 1), 1);
+        memory.write(AddressPattern::new(5, 0), 10);
+        assert_eq!(memory.address_count(), 1 << 40);
+        assert_eq!(memory.sum(), (1 << 39) * 2 + (1 << 39) + 8);
+        assert_eq!(memory.get(5), Some(10));
+        assert_eq!(memory.get(1 << 39), Some(1));
+        assert_eq!(memory.get(1 << 40), None);
+        assert!(memory.region_count() < 100);
+    }
+}
@@ -1,170 +1,215 @@
-use anyhow::Result;
+mod mask;
+mod memory;
+
+use crate::day_14::mask::Mask;
+use crate::day_14::memory::SparseMemory;
+use anyhow::{anyhow, Result};
 use std::collections::BTreeMap;
 use std::fs;
 
 #[derive(Debug, PartialEq, Eq)]
 enum Instruction {
-    Mask(String),
+    Mask(Mask),
     MemorySet(usize, u64),
 }
 
-fn line_to_instruction(line: &str) -> Instruction {
-    match &line[0..2] {
-        "ma" => Instruction::Mask(String::from(line.split_whitespace().last().unwrap())),
-        "me" => Instruction::MemorySet(
-            line.split(|c| c == '[' || c == ']').nth(1).unwrap().parse().unwrap(),
-            line.split_whitespace().last().unwrap().parse().unwrap(),
-        ),
-        _ => panic!("Malformed input"),
+fn line_to_instruction(line: &str) -> Result<Instruction> {
+    let (target, value) = line
+        .split_once(" = ")
+        .ok_or_else(|| anyhow!("Expected \"<target> = <value>\": {}", line))?;
+    if target == "mask" {
+        return Ok(Instruction::Mask(value.parse()?));
     }
+    let address = target
+        .strip_prefix("mem[")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| anyhow!("Unknown instruction: {}", line))?;
+    Ok(Instruction::MemorySet(
+        address.parse().map_err(|_| anyhow!("Invalid address: {}", line))?,
+        value.parse().map_err(|_| anyhow!("Invalid value: {}", line))?,
+    ))
 }
 
-fn parse_input(input: &str) -> Vec<Instruction> {
+fn parse_input(input: &str) -> Result<Vec<Instruction>> {
     input.split('\n').map(line_to_instruction).collect()
 }
 
 pub fn solve_part_1() -> Result<(), ()> {
-    let input = parse_input(&fs::read_to_string("./inputs/day14.txt").unwrap());
+    let input = parse_input(&fs::read_to_string("./inputs/day14.txt").unwrap()).map_err(|e| println!("{}", e))?;
     println!("{}", part_1(input));
     Ok(())
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
-    let input = parse_input(&fs::read_to_string("./inputs/day14.txt").unwrap());
+    let input = parse_input(&fs::read_to_string("./inputs/day14.txt").unwrap()).map_err(|e| println!("{}", e))?;
     println!("{}", part_2(input));
     Ok(())
 }
 
 fn part_1(input: Vec<Instruction>) -> u64 {
     let computer = Computer {
-        current_mask: "X".to_string(),
+        current_mask: "X".parse().unwrap(),
         memory_map: BTreeMap::new(),
     };
     let computer = run_program_on_computer(computer, input);
     computer.memory_map.iter().map(|(_, v)| v).sum()
 }
 
-fn part_2(input: Vec<Instruction>) -> u64 {
-    let computer = Computer {
-        current_mask: "X".to_string(),
-        memory_map: BTreeMap::new(),
-    };
-    let computer = run_program_on_computer_version_2(computer, input);
-    computer.memory_map.iter().map(|(_, v)| v).sum()
-}
-
-fn apply_bitmask(value: u64, mask: &str) -> u64 {
-    let or_with: String = mask.chars().map(|c| if c == 'X' { '0' } else { c }).collect();
-    let and_with: String = mask.chars().map(|c| if c == 'X' { '1' } else { c }).collect();
-    let or_with = u64::from_str_radix(&or_with, 2).unwrap();
-    let and_with = u64::from_str_radix(&and_with, 2).unwrap();
-    (value & and_with) | or_with
+fn part_2(input: Vec<Instruction>) -> u128 {
+    run_program_on_computer_version_2(input).sum()
 }
 
 struct Computer {
-    current_mask: String,
+    current_mask: Mask,
     memory_map: BTreeMap<usize, u64>,
 }
 
-struct MemoryDecoder {
-    and_mask: u64,
-    or_mask: u64,
-    floaters: Vec<usize>,
-}
-
-fn mask_to_decoder(mask: &str) -> MemoryDecoder {
-    let and_with: String = mask.chars().map(|c| if c == 'X' { '1' } else { '0' }).collect();
-    let and_with = !u64::from_str_radix(&and_with, 2).unwrap();
-    let or_with = mask.chars().map(|c| if c == 'X' { '0' } else { c }).collect::<String>();
-    let or_with = u64::from_str_radix(&or_with, 2).unwrap();
-    let floaters = mask
-        .chars()
-        .rev()
-        .enumerate()
-        .filter(|(_, c)| *c == 'X')
-        .map(|(i, _)| i)
-        .collect();
-    MemoryDecoder {
-        and_mask: and_with,
-        or_mask: or_with,
-        floaters,
-    }
-}
-
-// 0bx0x001
-// -> 0b000001
-// -> 0b001001
-// -> 0b100001
-// -> 0b101001
-// => stretching 00 -> 11 by an idx in a list pos.
-fn masks_from_stretched_bits(mask: u64, idxs: Vec<usize>) -> Vec<u64> {
-    let mut result = vec![];
-    for sub_mask_value in 0..(2usize.pow(idxs.len() as u32)) {
-        let mut stretched_sub_mask: u64 = 0;
-        let sub_mask_value = sub_mask_value as u64;
-        for (i, idx) in idxs.iter().enumerate() {
-            stretched_sub_mask |= ((sub_mask_value & (1 << i)) >> i) << idx;
-        }
-        result.push(mask | stretched_sub_mask);
-    }
-    result
-}
-
-fn addresses_decoded(address: u64, decoder: MemoryDecoder) -> Vec<u64> {
-    let base = (address | decoder.or_mask) & decoder.and_mask;
-    masks_from_stretched_bits(base, decoder.floaters)
-}
-
 fn run_program_on_computer(mut computer: Computer, program: Vec<Instruction>) -> Computer {
     for instruction in program.iter() {
         match instruction {
-            Instruction::Mask(mask) => computer.current_mask = mask.clone(),
+            Instruction::Mask(mask) => computer.current_mask = *mask,
             Instruction::MemorySet(location, value) => {
                 computer
                     .memory_map
-                    .insert(*location, apply_bitmask(*value, &computer.current_mask));
+                    .insert(*location, computer.current_mask.apply(*value));
             }
         }
     }
     computer
 }
 
-fn run_program_on_computer_version_2(mut computer: Computer, program: Vec<Instruction>) -> Computer {
+fn run_program_on_computer_version_2(program: Vec<Instruction>) -> SparseMemory {
+    let mut memory = SparseMemory::new();
+    let mut current_mask: Mask = "X".parse().unwrap();
     for instruction in program.iter() {
         match instruction {
-            Instruction::Mask(mask) => computer.current_mask = mask.clone(),
+            Instruction::Mask(mask) => current_mask = *mask,
             Instruction::MemorySet(location, value) => {
-                let decoder = mask_to_decoder(&computer.current_mask);
-                let memory_locations = addresses_decoded(*location as u64, decoder);
-                for x in memory_locations {
-                    computer.memory_map.insert(x as usize, *value);
-                }
+                memory.write(current_mask.address_pattern(*location as u64), *value);
             }
         }
     }
-    computer
+    memory
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    // 0bx0x001
+    // -> 0b000001
+    // -> 0b001001
+    // -> 0b100001
+    // -> 0b101001
+    // => stretching 00 -> 11 by an idx in a list pos.
+    fn masks_from_stretched_bits(mask: u64, idxs: Vec<usize>) -> Vec<u64> {
+        let mut result = vec![];
+        for sub_mask_value in 0..(2usize.pow(idxs.len() as u32)) {
+            let mut stretched_sub_mask: u64 = 0;
+            let sub_mask_value = sub_mask_value as u64;
+            for (i, idx) in idxs.iter().enumerate() {
+                stretched_sub_mask |= ((sub_mask_value & (1 << i)) >> i) << idx;
+            }
+            result.push(mask | stretched_sub_mask);
+        }
+        result
+    }
+
+    fn addresses_decoded(address: u64, mask: &Mask) -> Vec<u64> {
+        let base = (address | mask.or_mask()) & !mask.floating();
+        let floaters = (0..64).filter(|i| mask.floating() & 1 << i != 0).collect();
+        masks_from_stretched_bits(base, floaters)
+    }
+
+    // Version 2 as it was, writing every address each floating mask expands to.
+    fn run_reference_version_2(program: &[Instruction]) -> u64 {
+        let mut memory = BTreeMap::new();
+        let mut mask: Mask = "X".parse().unwrap();
+        for instruction in program {
+            match instruction {
+                Instruction::Mask(new_mask) => mask = *new_mask,
+                Instruction::MemorySet(location, value) => {
+                    for address in addresses_decoded(*location as u64, &mask) {
+                        memory.insert(address, *value);
+                    }
+                }
+            }
+        }
+        memory.values().sum()
+    }
+
+    // A program of random masks with up to `max_floating` floating bits, writing to small addresses
+    // so that the writes overlap.
+    fn random_program(seed: u64, length: usize, max_floating: usize) -> Vec<Instruction> {
+        let mut state = seed * 2 + 1;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut program = vec![];
+        for _ in 0..length {
+            let mut mask: Vec<char> = (0..36).map(|_| if next() % 4 == 0 { '1' } else { '0' }).collect();
+            for _ in 0..(next() as usize % (max_floating + 1)) {
+                mask[24 + next() as usize % 12] = 'X';
+            }
+            let mask: String = mask.into_iter().collect();
+            program.push(Instruction::Mask(mask.parse().unwrap()));
+            for _ in 0..3 {
+                program.push(Instruction::MemorySet(next() as usize % 4096, next() % 1000));
+            }
+        }
+        program
+    }
+
     #[test]
     fn test_part_1() {
-        let input = parse_input(&fs::read_to_string("./test_inputs/day14").unwrap());
+        let input = parse_input(&fs::read_to_string("./test_inputs/day14").unwrap()).unwrap();
         assert_eq!(part_1(input), 165);
     }
 
     #[test]
     fn test_part_2() {
-        let input = parse_input(&fs::read_to_string("./test_inputs/day14_2.txt").unwrap());
+        let input = parse_input(&fs::read_to_string("./test_inputs/day14_2.txt").unwrap()).unwrap();
         assert_eq!(part_2(input), 208);
     }
 
+    #[test]
+    fn test_version_2_matches_expanding_addresses() {
+        let input = parse_input(
+            "mask = 000000000000000000000000000000X1001X\n\
+             mem[42] = 100\n\
+             mask = 00000000000000000000000000000000X0XX\n\
+             mem[26] = 1",
+        )
+        .unwrap();
+        assert_eq!(part_2(input), 208);
+        for seed in 0..20 {
+            let program = random_program(seed, 30, 8);
+            let expected = run_reference_version_2(&program) as u128;
+            assert_eq!(part_2(program), expected);
+        }
+    }
+
+    #[test]
+    fn test_many_floating_bits() {
+        let input = parse_input(
+            "mask = XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX\n\
+             mem[0] = 1\n\
+             mask = 0000XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX\n\
+             mem[0] = 3",
+        )
+        .unwrap();
+        assert_eq!(part_2(input), (1 << 36) + 2 * (1 << 32));
+    }
+
     #[test]
     fn test_masking() {
-        assert_eq!(apply_bitmask(11, "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"), 73);
-        assert_eq!(apply_bitmask(101, "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"), 101);
-        assert_eq!(apply_bitmask(0, "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X"), 64)
+        let mask: Mask = "XXXXXXXXXXXXXXXXXXXXXXXXXXXXX1XXXX0X".parse().unwrap();
+        assert_eq!(mask.apply(11), 73);
+        assert_eq!(mask.apply(101), 101);
+        assert_eq!(mask.apply(0), 64)
     }
     #[test]
     fn test_parsing() {
@@ -173,19 +218,25 @@ mod tests {
                             mem[54146] = 1656\n\
                             mem[30135] = 4799584";
         let correct = vec![
-            Instruction::Mask("0010X01001X010000110100000X000010X11".to_string()),
+            Instruction::Mask("0010X01001X010000110100000X000010X11".parse().unwrap()),
             Instruction::MemorySet(41717, 288),
             Instruction::MemorySet(54146, 1656),
             Instruction::MemorySet(30135, 4799584),
         ];
 
-        assert_eq!(parse_input(input), correct);
+        assert_eq!(parse_input(input).unwrap(), correct);
+
+        let error = |input: &str| parse_input(input).unwrap_err().to_string();
+        assert_eq!(error("mask = 10Y"), "Invalid mask character 'Y' at position 3: 10Y");
+        assert_eq!(error("mem[12] = -4"), "Invalid value: mem[12] = -4");
+        assert_eq!(error("mem(12) = 4"), "Unknown instruction: mem(12) = 4");
+        assert!(error("mask 0101").starts_with("Expected"));
     }
 
     #[test]
     fn test_address_decoded() {
-        let decoder = mask_to_decoder("000000000000000000000000000000X1001X");
-        assert_eq!(addresses_decoded(42, decoder), vec![26, 27, 58, 59]);
+        let mask = "000000000000000000000000000000X1001X".parse().unwrap();
+        assert_eq!(addresses_decoded(42, &mask), vec![26, 27, 58, 59]);
     }
 
     #[test]
@@ -1,94 +1,160 @@
-use anyhow::Result;
-use hashbrown::HashMap;
-use std::collections::VecDeque;
+mod stats;
+
+use anyhow::{anyhow, Result};
+use std::fs;
 
 pub fn solve_part_1() -> Result<(), ()> {
-    println!("{}", part_1(vec![12, 20, 0, 6, 1, 17, 7]));
+    let input = parse_input(&fs::read_to_string("./inputs/day15.txt").unwrap()).map_err(|e| println!("{}", e))?;
+    println!("{}", part_1(input));
     Ok(())
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
-    println!("{}", part_2(vec![12, 20, 0, 6, 1, 17, 7]));
+    let input = parse_input(&fs::read_to_string("./inputs/day15.txt").unwrap()).map_err(|e| println!("{}", e))?;
+    println!("{}", part_2(input));
+    Ok(())
+}
+
+// Plays as far as the given part does, then prints statistics about the run, including when
+// `spoken` was first said if there is one, and writes them to `csv_path` as CSV if there is one.
+pub fn solve_with_stats(part: usize, spoken: Option<u32>, csv_path: Option<&str>) -> Result<(), ()> {
+    let turns = match part {
+        1 => 2020,
+        2 => 30_000_000,
+        _ => {
+            println!("There is no part {}", part);
+            return Err(());
+        }
+    };
+    let input = parse_input(&fs::read_to_string("./inputs/day15.txt").unwrap()).map_err(|e| println!("{}", e))?;
+    let game = MemoryGame::with_turns(input, turns as usize).map_err(|e| println!("{}", e))?;
+    let stats = stats::analyze(game, turns, 16);
+    println!("{}", stats);
+    if let Some(number) = spoken {
+        match stats.first_occurrence(number) {
+            Some(turn) => println!("{} was first spoken on turn {}", number, turn),
+            None => println!("{} was never spoken", number),
+        }
+    }
+    if let Some(path) = csv_path {
+        fs::write(path, stats.to_csv()).map_err(|e| println!("{}", e))?;
+    }
     Ok(())
 }
 
+fn parse_input(input: &str) -> Result<Vec<u32>> {
+    input
+        .trim()
+        .split(',')
+        .map(|number| {
+            number
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid starting number: {:?}", number))
+        })
+        .collect()
+}
+
 fn part_1(input: Vec<u32>) -> u32 {
-    find_nth_in_sequence(&input, 2020)
+    let mut game = MemoryGame::new(input).expect("Need starting numbers");
+    game.nth(2019).unwrap()
 }
 
 fn part_2(input: Vec<u32>) -> u32 {
-    find_nth_in_sequence_efficient(&input, 30000000)
+    let game = MemoryGame::with_turns(input, 30_000_000).expect("Need starting numbers");
+    game.nth_turns(&[30_000_000])[0]
 }
 
-fn find_nth_in_sequence(input: &[u32], n: usize) -> u32 {
-    let mut sequence = input.to_vec();
-    let starting_turn = input.len() + 1;
+// The numbers spoken in the game, turn by turn, starting with the starting numbers. Remembers the
+// last turn each number was spoken on in a flat array indexed by the number, which never needs to
+// be longer than the number of turns played or the largest starting number.
+struct MemoryGame {
+    starting_numbers: Vec<u32>,
+    // The last turn each number was spoken on, counting from 1, or 0 if it hasn't been.
+    last_spoken: Vec<u32>,
+    turns_played: u32,
+    next_number: u32,
+}
 
-    for turn in starting_turn..=n {
-        let comparing = sequence.last().unwrap();
-        let to_push = sequence[0..sequence.len() - 1]
-            .iter()
-            .enumerate()
-            .filter(|(_, value)| **value == *comparing)
-            .max_by_key(|(i, _)| *i)
-            .map(|(most_recent_idx, _)| (turn - 1 - (most_recent_idx + 1)))
-            .unwrap_or(0);
-        sequence.push(to_push as u32);
+impl MemoryGame {
+    fn new(starting_numbers: Vec<u32>) -> Result<MemoryGame> {
+        MemoryGame::with_turns(starting_numbers, 0)
     }
-    *sequence.last().unwrap()
-}
 
-type ValuesToTurnsMap = HashMap<u32, VecDeque<usize>>;
-fn handle_if_in_map(locations_map: &mut ValuesToTurnsMap, looking_for: u32, turn: usize) -> u32 {
-    let turn_list = locations_map.get_mut(&looking_for).unwrap();
-
-    // This only occurs when it was the last value entered, so there is no previous occurrence
-    let to_insert = if turn_list.len() == 1 {
-        (0, turn)
-    } else {
-        // if the list is of length > 1,  add or alter a key in the map with value (turn  - 1  - list[length-2])
-        let new_value = (turn - 1 - turn_list[0]) as u32;
-        (new_value, turn)
-    };
-    add_or_append_to_key(locations_map, to_insert.0, to_insert.1)
-}
+    // A game with room for `turns` turns set aside up front.
+    fn with_turns(starting_numbers: Vec<u32>, turns: usize) -> Result<MemoryGame> {
+        let largest = *starting_numbers
+            .iter()
+            .max()
+            .ok_or_else(|| anyhow!("The game needs at least one starting number"))?;
+        Ok(MemoryGame {
+            starting_numbers,
+            last_spoken: vec![0; turns.max(largest as usize + 1)],
+            turns_played: 0,
+            next_number: 0,
+        })
+    }
 
-fn add_or_append_to_key(locations_map: &mut ValuesToTurnsMap, key: u32, turn: usize) -> u32 {
-    let current = locations_map.entry(key).or_insert_with(|| VecDeque::with_capacity(2));
-    if current.len() < 2 {
-        current.push_back(turn);
-    } else {
-        current.pop_front();
-        current.push_back(turn);
+    // The numbers spoken on each of the given turns, counting from 1, played in a single pass up
+    // to the last of them.
+    fn nth_turns(mut self, turns: &[usize]) -> Vec<u32> {
+        let last_turn = turns.iter().copied().max().unwrap_or(0);
+        let mut spoken = vec![0; turns.len()];
+        let mut wanted: Vec<(usize, usize)> = turns.iter().copied().enumerate().collect();
+        wanted.sort_unstable_by_key(|&(_, turn)| turn);
+        let mut wanted = wanted.into_iter().peekable();
+        for (turn, number) in (1..=last_turn).zip(&mut self) {
+            while let Some((index, _)) = wanted.next_if(|&(_, wanted_turn)| wanted_turn == turn) {
+                spoken[index] = number;
+            }
+        }
+        spoken
     }
-    key
 }
 
-fn find_nth_in_sequence_efficient(input: &[u32], n: usize) -> u32 {
-    let mut locations_map: ValuesToTurnsMap = ValuesToTurnsMap::new();
-    for (turn, value) in input.iter().enumerate() {
-        locations_map.entry(*value).or_insert_with(|| {
-            let mut value = VecDeque::with_capacity(2);
-            value.push_back(turn + 1);
-            value
-        });
-    }
-    let starting_turn = input.len() + 1;
-    let mut looking_for = *input.last().unwrap();
-    for turn in starting_turn..=n {
-        looking_for = if locations_map.get(&looking_for).is_some() {
-            handle_if_in_map(&mut locations_map, looking_for, turn)
-        } else {
-            add_or_append_to_key(&mut locations_map, 0, turn)
+impl Iterator for MemoryGame {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let number = self
+            .starting_numbers
+            .get(self.turns_played as usize)
+            .copied()
+            .unwrap_or(self.next_number);
+        self.turns_played += 1;
+        if number as usize >= self.last_spoken.len() {
+            let length = (number as usize + 1).max(self.last_spoken.len() * 2);
+            self.last_spoken.resize(length, 0);
         }
+        let previous = std::mem::replace(&mut self.last_spoken[number as usize], self.turns_played);
+        self.next_number = if previous == 0 { 0 } else { self.turns_played - previous };
+        Some(number)
     }
-    println!("Ended with {} entries in the map", locations_map.len());
-    looking_for
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    // The game as it was first played, searching back through every number spoken so far.
+    fn find_nth_in_sequence(input: &[u32], n: usize) -> u32 {
+        let mut sequence = input.to_vec();
+        let starting_turn = input.len() + 1;
+
+        for turn in starting_turn..=n {
+            let comparing = sequence.last().unwrap();
+            let to_push = sequence[0..sequence.len() - 1]
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| **value == *comparing)
+                .max_by_key(|(i, _)| *i)
+                .map(|(most_recent_idx, _)| turn - 1 - (most_recent_idx + 1))
+                .unwrap_or(0);
+            sequence.push(to_push as u32);
+        }
+        *sequence.last().unwrap()
+    }
+
     #[test]
     fn test_simple() {
         let input = vec![0, 3, 6];
@@ -104,21 +170,30 @@ mod tests {
 
     #[test]
     fn test_efficient() {
-        let input = vec![0, 3, 6];
-        assert_eq!(find_nth_in_sequence_efficient(&input, 4), 0);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 5), 3);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 6), 3);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 7), 1);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 8), 0);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 9), 4);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 10), 0);
-        assert_eq!(find_nth_in_sequence_efficient(&input, 2020), 436);
+        let game = MemoryGame::new(vec![0, 3, 6]).unwrap();
+        assert_eq!(game.take(10).collect::<Vec<_>>(), vec![0, 3, 6, 0, 3, 3, 1, 0, 4, 0]);
+        let game = MemoryGame::new(vec![0, 3, 6]).unwrap();
+        assert_eq!(game.nth_turns(&[2020, 4, 9, 2020, 1]), vec![436, 0, 4, 436, 0]);
+        assert_eq!(part_1(parse_input("1,3,2\n").unwrap()), 1);
+        assert_eq!(MemoryGame::new(vec![3, 1, 2]).unwrap().nth(2019), Some(1836));
+        assert!(MemoryGame::new(vec![]).is_err());
+        assert!(parse_input("1,x,2").is_err());
+    }
+
+    #[test]
+    fn test_matches_slow_sequence() {
+        for input in [vec![2, 1, 3], vec![1, 2, 3], vec![5, 5, 0, 100], vec![7]] {
+            let game = MemoryGame::new(input.clone()).unwrap();
+            for (turn, number) in (1..=300).zip(game).skip(input.len()) {
+                assert_eq!(number, find_nth_in_sequence(&input, turn), "{:?} turn {}", input, turn);
+            }
+        }
     }
 
     #[test]
     #[ignore]
     fn test_long_example() {
         let input = vec![0, 3, 6];
-        assert_eq!(find_nth_in_sequence_efficient(&input, 30000000), 175594);
+        assert_eq!(part_2(input), 175594);
     }
 }
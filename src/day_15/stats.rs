@@ -0,0 +1,151 @@
+use crate::day_15::MemoryGame;
+use std::collections::VecDeque;
+use std::fmt;
+
+// A stretch at the end of a run where the numbers repeat every `period` turns, from `start` on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: u32,
+    pub period: u32,
+}
+
+#[derive(Debug)]
+pub struct Stats {
+    pub turns: u32,
+    pub distinct_numbers: usize,
+    // The largest gap between a number being spoken and spoken again, and the turn that said so.
+    pub largest_gap: Option<(u32, u32)>,
+    pub cycle: Option<Cycle>,
+    // The first turn each number was spoken on, or 0 if it wasn't.
+    first_spoken: Vec<u32>,
+}
+
+impl Stats {
+    pub fn first_occurrence(&self, number: u32) -> Option<u32> {
+        match self.first_spoken.get(number as usize) {
+            Some(&turn) if turn != 0 => Some(turn),
+            _ => None,
+        }
+    }
+
+    // Rows of "statistic,value", with empty values for what didn't happen.
+    pub fn to_csv(&self) -> String {
+        let optional = |value: Option<u32>| value.map_or(String::new(), |value| value.to_string());
+        let rows = [
+            ("turns", self.turns.to_string()),
+            ("distinct_numbers", self.distinct_numbers.to_string()),
+            ("largest_gap", optional(self.largest_gap.map(|(gap, _)| gap))),
+            ("largest_gap_turn", optional(self.largest_gap.map(|(_, turn)| turn))),
+            ("cycle_start", optional(self.cycle.map(|cycle| cycle.start))),
+            ("cycle_period", optional(self.cycle.map(|cycle| cycle.period))),
+        ];
+        let mut csv = String::from("statistic,value\n");
+        for (name, value) in rows.iter() {
+            csv += &format!("{},{}\n", name, value);
+        }
+        csv
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} turns, {} distinct numbers", self.turns, self.distinct_numbers)?;
+        if let Some((gap, turn)) = self.largest_gap {
+            write!(f, ", largest gap {} on turn {}", gap, turn)?;
+        }
+        match self.cycle {
+            Some(cycle) => write!(f, ", repeating every {} turns from turn {}", cycle.period, cycle.start),
+            None => write!(f, ", no cycle"),
+        }
+    }
+}
+
+// Plays `turns` turns of the game, looking for cycles of up to `max_period` turns at the end.
+pub fn analyze(game: MemoryGame, turns: u32, max_period: u32) -> Stats {
+    let starting_turns = game.starting_numbers.len() as u32;
+    let mut first_spoken = vec![];
+    let mut largest_gap = None;
+    let mut recent = VecDeque::with_capacity(max_period as usize + 1);
+    // How many turns in a row, ending now, have matched the turn `period` before them.
+    let mut matching_runs = vec![0; max_period as usize + 1];
+    for (turn, number) in (1..=turns).zip(game) {
+        if number as usize >= first_spoken.len() {
+            first_spoken.resize(number as usize + 1, 0);
+        }
+        if first_spoken[number as usize] == 0 {
+            first_spoken[number as usize] = turn;
+        }
+        if turn > starting_turns && !matches!(largest_gap, Some((gap, _)) if gap >= number) {
+            largest_gap = Some((number, turn));
+        }
+        recent.push_front(number);
+        recent.truncate(max_period as usize + 1);
+        for (period, run) in matching_runs.iter_mut().enumerate().skip(1) {
+            *run = match recent.get(period) {
+                Some(&earlier) if earlier == number => *run + 1,
+                _ => 0,
+            };
+        }
+    }
+    // A whole period has to have repeated to count.
+    let cycle = (1..=max_period)
+        .find(|&period| matching_runs[period as usize] >= period)
+        .map(|period| Cycle {
+            start: turns - matching_runs[period as usize] - period + 1,
+            period,
+        });
+    Stats {
+        turns,
+        distinct_numbers: first_spoken.iter().filter(|&&turn| turn != 0).count(),
+        largest_gap,
+        cycle,
+        first_spoken,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        // 0, 3, 6, 0, 3, 3, 1, 0, 4, 0
+        let stats = analyze(MemoryGame::new(vec![0, 3, 6]).unwrap(), 10, 4);
+        assert_eq!(stats.distinct_numbers, 5);
+        assert_eq!(stats.largest_gap, Some((4, 9)));
+        assert_eq!(stats.first_occurrence(0), Some(1));
+        assert_eq!(stats.first_occurrence(1), Some(7));
+        assert_eq!(stats.first_occurrence(4), Some(9));
+        assert_eq!(stats.first_occurrence(2), None);
+        assert_eq!(stats.first_occurrence(1000), None);
+        assert_eq!(stats.cycle, None);
+        assert_eq!(
+            stats.to_string(),
+            "10 turns, 5 distinct numbers, largest gap 4 on turn 9, no cycle"
+        );
+        assert_eq!(
+            stats.to_csv(),
+            "statistic,value\nturns,10\ndistinct_numbers,5\nlargest_gap,4\nlargest_gap_turn,9\n\
+             cycle_start,\ncycle_period,\n"
+        );
+
+        let stats = analyze(MemoryGame::new(vec![0, 3, 6]).unwrap(), 2020, 16);
+        assert_eq!(stats.cycle, None);
+        assert!(stats.largest_gap.unwrap().0 < 2020);
+    }
+
+    #[test]
+    fn test_cycles() {
+        // The game itself doesn't cycle, so this is about the detection: the starting numbers are
+        // as long as the run, and end by repeating "7, 8, 9" from turn 3.
+        let game = MemoryGame::new(vec![1, 2, 7, 8, 9, 7, 8, 9, 7, 8]).unwrap();
+        let stats = analyze(game, 10, 5);
+        assert_eq!(stats.cycle, Some(Cycle { start: 3, period: 3 }));
+        assert_eq!(stats.largest_gap, None);
+
+        let game = MemoryGame::new(vec![1, 2, 7, 8, 9, 7, 8]).unwrap();
+        assert_eq!(analyze(game, 7, 5).cycle, None);
+        let game = MemoryGame::new(vec![4, 4, 4]).unwrap();
+        assert_eq!(analyze(game, 3, 2).cycle, Some(Cycle { start: 1, period: 1 }));
+    }
+}
@@ -1,8 +1,12 @@
+use crate::matching::{Matcher, Outcome};
+use anyhow::{anyhow, Context};
 use hashbrown::HashMap;
-use lazy_static::lazy_static;
-use regex::Regex;
-use std::fs;
+use itertools::Itertools;
+use std::fmt::Display;
 use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+use std::{fmt, fs};
 
 pub fn solve_part_1() -> Result<(), ()> {
     let input = parse_input(&fs::read_to_string("./inputs/day16.txt").unwrap());
@@ -12,51 +16,148 @@ pub fn solve_part_1() -> Result<(), ()> {
 
 pub fn solve_part_2() -> Result<(), ()> {
     let input = parse_input(&fs::read_to_string("./inputs/day16.txt").unwrap());
-    let result = part_2(input);
-    println!("{}", result);
+    let tickets = decode_tickets(input).map_err(|e| println!("{}", e))?;
+    println!("{}", part_2(&tickets));
+    Ok(())
+}
+
+// Solves part 2, then writes every decoded ticket to `tickets.csv` and `tickets.json` in `export_dir`.
+pub fn solve_part_2_with_export(export_dir: &str) -> Result<(), ()> {
+    let input = parse_input(&fs::read_to_string("./inputs/day16.txt").unwrap());
+    let tickets = decode_tickets(input).map_err(|e| println!("{}", e))?;
+    println!("{}", part_2(&tickets));
+    fs::create_dir_all(export_dir).map_err(|e| println!("{}", e))?;
+    let dir = Path::new(export_dir);
+    fs::write(dir.join("tickets.csv"), tickets_to_csv(&tickets)).map_err(|e| println!("{}", e))?;
+    fs::write(dir.join("tickets.json"), tickets_to_json(&tickets)).map_err(|e| println!("{}", e))?;
+    println!("Wrote {} tickets to {}", tickets.len(), export_dir);
     Ok(())
 }
 
 fn part_1(problem: Problem) -> u32 {
-    problem
-        .nearby_tickets
+    validate_tickets(&problem.criteria, &problem.nearby_tickets)
         .iter()
-        .flat_map(|ticket| get_failing_values_from_ticket(&problem.criteria, ticket).into_iter())
+        .flat_map(|ticket| ticket.invalid_fields.iter())
+        .map(|field| field.value)
         .sum()
 }
 
-fn part_2(problem: Problem) -> usize {
-    let my_ticket = problem.my_ticket.clone();
-    let criteria_to_columns = determine_criteria_to_columns(problem);
-
-    let relevant_columns: Vec<_> = criteria_to_columns
+fn part_2(tickets: &[DecodedTicket]) -> usize {
+    let my_ticket = tickets
         .iter()
-        .filter(|(k, _)| (0..=5).contains(*k))
-        .map(|(_, v)| v)
-        .collect();
-    relevant_columns
+        .find(|ticket| ticket.source == TicketSource::Mine)
+        .unwrap();
+    my_ticket
+        .fields
         .iter()
-        .map(|col_idx| my_ticket[**col_idx] as usize)
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with("departure"))
+        .filter_map(|name| my_ticket.get(name))
+        .map(|value| value as usize)
         .product()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Problem {
-    criteria: Vec<(RangeInclusive<u32>, RangeInclusive<u32>)>,
+    criteria: Vec<Rule>,
     my_ticket: Vec<u32>,
     nearby_tickets: Vec<Vec<u32>>,
 }
 
-fn criteria_from_input_line(line: &str) -> (RangeInclusive<u32>, RangeInclusive<u32>) {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r".*+: (\d+)-(\d+) or (\d+)-(\d+)").unwrap();
+type TicketRange = RangeInclusive<u32>;
+
+// A named field rule, e.g. "seat: 13-40 or 45-50 or 52 or not 20-22". A value passes when it is
+// in any of the allowed ranges or values and in none of the ones marked with "not".
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    name: String,
+    allowed: Vec<TicketRange>,
+    excluded: Vec<TicketRange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Violation {
+    OutsideAllowed,
+    Excluded(TicketRange),
+}
+
+impl Rule {
+    fn check(&self, value: u32) -> Result<(), Violation> {
+        if let Some(range) = self.excluded.iter().find(|range| range.contains(&value)) {
+            Err(Violation::Excluded(range.clone()))
+        } else if self.allowed.iter().any(|range| range.contains(&value)) {
+            Ok(())
+        } else {
+            Err(Violation::OutsideAllowed)
+        }
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.check(value).is_ok()
+    }
+}
+
+fn range_to_string(range: &TicketRange) -> String {
+    if range.start() == range.end() {
+        range.start().to_string()
+    } else {
+        format!("{}-{}", range.start(), range.end())
+    }
+}
+
+fn range_from_str(input: &str) -> anyhow::Result<TicketRange> {
+    let mut bounds = input.splitn(2, '-').map(|bound| bound.trim().parse::<u32>());
+    let start = bounds
+        .next()
+        .unwrap()
+        .with_context(|| format!("Bad range: {}", input))?;
+    let end = bounds
+        .next()
+        .unwrap_or(Ok(start))
+        .with_context(|| format!("Bad range: {}", input))?;
+    if start > end {
+        return Err(anyhow!("Range {} runs backwards", input));
+    }
+    Ok(start..=end)
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> anyhow::Result<Self> {
+        let (name, ranges) = line
+            .rsplitn(2, ": ")
+            .collect_tuple()
+            .map(|(ranges, name)| (name, ranges))
+            .ok_or_else(|| anyhow!("Expected \"<name>: <ranges>\", found: {}", line))?;
+        let mut allowed = vec![];
+        let mut excluded = vec![];
+        for part in ranges.split(" or ") {
+            match part.trim().strip_prefix("not ") {
+                Some(range) => excluded.push(range_from_str(range)?),
+                None => allowed.push(range_from_str(part)?),
+            }
+        }
+        if allowed.is_empty() {
+            return Err(anyhow!("Rule {} doesn't allow any values", name));
+        }
+        Ok(Rule {
+            name: name.to_string(),
+            allowed,
+            excluded,
+        })
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = self.allowed.iter().map(range_to_string).chain(
+            self.excluded
+                .iter()
+                .map(|range| format!("not {}", range_to_string(range))),
+        );
+        write!(f, "{}: {}", self.name, parts.format(" or "))
     }
-    let captures = RE.captures(line).unwrap();
-    let min_1 = captures.get(1).unwrap().as_str().parse::<u32>().unwrap();
-    let max_1 = captures.get(2).unwrap().as_str().parse::<u32>().unwrap();
-    let min_2 = captures.get(3).unwrap().as_str().parse::<u32>().unwrap();
-    let max_2 = captures.get(4).unwrap().as_str().parse::<u32>().unwrap();
-    (min_1..=max_1, min_2..=max_2)
 }
 
 fn parse_input(input: &str) -> Problem {
@@ -65,7 +166,10 @@ fn parse_input(input: &str) -> Problem {
     let my_ticket = input_iter.next().unwrap();
     let nearby_tickets = input_iter.next().unwrap();
 
-    let criteria = criteria.split('\n').map(criteria_from_input_line).collect();
+    let criteria = criteria
+        .split('\n')
+        .map(|line| line.parse().expect("Malformed rule"))
+        .collect();
     let my_ticket = my_ticket
         .split('\n')
         .nth(1)
@@ -84,41 +188,91 @@ fn parse_input(input: &str) -> Problem {
         nearby_tickets,
     }
 }
-type TicketRange = RangeInclusive<u32>;
+// A value that satisfies none of the rules, with the way it broke each of them.
+#[derive(Debug, PartialEq, Eq)]
+struct FieldDiagnostic {
+    position: usize,
+    value: u32,
+    broken_rules: Vec<(String, Violation)>,
+}
 
-#[inline]
-fn check_against_all_criteria(criteria: &[(TicketRange, TicketRange)], value: u32) -> bool {
-    criteria
-        .iter()
-        .any(|(range_0, range_1)| range_0.contains(&value) || range_1.contains(&value))
+#[derive(Debug, PartialEq, Eq)]
+struct TicketDiagnostic {
+    ticket: usize,
+    invalid_fields: Vec<FieldDiagnostic>,
+}
+
+impl Display for FieldDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reasons = self.broken_rules.iter().map(|(name, violation)| match violation {
+            Violation::OutsideAllowed => format!("{} (not allowed)", name),
+            Violation::Excluded(range) => format!("{} (excludes {})", name, range_to_string(range)),
+        });
+        write!(
+            f,
+            "field {}: {} breaks {}",
+            self.position,
+            self.value,
+            reasons.format(", ")
+        )
+    }
+}
+
+impl Display for TicketDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for field in self.invalid_fields.iter() {
+            writeln!(f, "ticket {}, {}", self.ticket, field)?;
+        }
+        Ok(())
+    }
 }
 
 #[inline]
-fn get_failing_values_from_ticket(criteria: &[(TicketRange, TicketRange)], ticket: &[u32]) -> Vec<u32> {
+fn check_against_all_criteria(criteria: &[Rule], value: u32) -> bool {
+    criteria.iter().any(|rule| rule.contains(value))
+}
+
+fn validate_ticket(criteria: &[Rule], ticket: &[u32]) -> Vec<FieldDiagnostic> {
     ticket
         .iter()
-        .copied()
-        .filter(|value| !check_against_all_criteria(criteria, *value))
+        .enumerate()
+        .filter(|(_, value)| !check_against_all_criteria(criteria, **value))
+        .map(|(position, value)| FieldDiagnostic {
+            position,
+            value: *value,
+            broken_rules: criteria
+                .iter()
+                .filter_map(|rule| rule.check(*value).err().map(|violation| (rule.name.clone(), violation)))
+                .collect(),
+        })
+        .collect()
+}
+
+// Diagnostics for every ticket with at least one invalid value, by index into `tickets`.
+fn validate_tickets(criteria: &[Rule], tickets: &[Vec<u32>]) -> Vec<TicketDiagnostic> {
+    tickets
+        .iter()
+        .enumerate()
+        .map(|(ticket, values)| TicketDiagnostic {
+            ticket,
+            invalid_fields: validate_ticket(criteria, values),
+        })
+        .filter(|diagnostic| !diagnostic.invalid_fields.is_empty())
         .collect()
 }
 
 #[inline]
-fn check_ticket_validity(criteria: &[(TicketRange, TicketRange)], ticket: &[u32]) -> bool {
-    !ticket.iter().any(|value| !check_against_all_criteria(criteria, *value))
+fn check_ticket_validity(criteria: &[Rule], ticket: &[u32]) -> bool {
+    ticket.iter().all(|value| check_against_all_criteria(criteria, *value))
 }
 
 #[inline]
-fn check_all_against_criteria((range_0, range_1): &(TicketRange, TicketRange), values: &[u32]) -> bool {
-    values
-        .iter()
-        .all(|value| range_0.contains(value) || range_1.contains(value))
+fn check_all_against_criteria(rule: &Rule, values: &[u32]) -> bool {
+    values.iter().all(|value| rule.contains(*value))
 }
 
 #[inline]
-fn get_matching_columns_for_criteria(
-    criteria: &(TicketRange, TicketRange),
-    columns: &HashMap<usize, Vec<u32>>,
-) -> Vec<usize> {
+fn get_matching_columns_for_criteria(criteria: &Rule, columns: &HashMap<usize, Vec<u32>>) -> Vec<usize> {
     columns
         .iter()
         .filter(|(_, col)| check_all_against_criteria(&criteria, *col))
@@ -134,34 +288,151 @@ fn determine_criteria_to_columns(mut problem: Problem) -> HashMap<usize, usize>
         .iter()
         .filter(|ticket| check_ticket_validity(&problem.criteria, *ticket));
     let valid_tickets_as_cols = convert_tickets_to_cols(valid_tickets, problem.my_ticket.len());
-    let mut all_criteria_map: HashMap<usize, (TicketRange, TicketRange)> =
-        problem.criteria.iter().cloned().enumerate().collect();
-    let mut correct_criteria_map = HashMap::new();
-    let mut idx_to_cols: HashMap<usize, Vec<_>> = valid_tickets_as_cols.into_iter().enumerate().collect();
+    let idx_to_cols: HashMap<usize, Vec<_>> = valid_tickets_as_cols.into_iter().enumerate().collect();
+    let criteria_to_matching_columns = problem
+        .criteria
+        .iter()
+        .enumerate()
+        .map(|(key, criteria)| {
+            let matching = get_matching_columns_for_criteria(criteria, &idx_to_cols);
+            (key, matching.into_iter().collect())
+        })
+        .collect();
+    match Matcher::new(criteria_to_matching_columns).solve().outcome {
+        Outcome::Unique(criteria_to_columns) => criteria_to_columns.into_iter().collect(),
+        Outcome::Multiple(_) => panic!("The columns fit the criteria in more than one way"),
+        Outcome::Impossible(criteria) => panic!("Not enough columns fit the criteria {:?}", criteria),
+    }
+}
 
-    while !all_criteria_map.is_empty() {
-        let criteria_to_matching_columns: HashMap<usize, Vec<usize>> = all_criteria_map
-            .iter()
-            .map(|(key, criteria)| (*key, get_matching_columns_for_criteria(criteria, &idx_to_cols)))
-            .collect();
-        if criteria_to_matching_columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TicketSource {
+    Mine,
+    Nearby(usize),
+}
+
+// A ticket's values keyed by field name, in the order the rules are listed. Tickets with values
+// no rule allows are still decoded, but marked as invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DecodedTicket {
+    source: TicketSource,
+    valid: bool,
+    fields: Vec<(String, u32)>,
+}
+
+impl DecodedTicket {
+    fn get(&self, name: &str) -> Option<u32> {
+        self.fields
             .iter()
-            .filter(|(_, v)| v.len() == 1)
-            .count()
-            > 1
-        {
-            println!("More than one column matched. Oh dear");
-        }
-        let current_match = criteria_to_matching_columns
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| *value)
+    }
+}
+
+// Our ticket first, then every nearby ticket in input order.
+fn decode_tickets(problem: Problem) -> anyhow::Result<Vec<DecodedTicket>> {
+    let criteria_to_columns = determine_criteria_to_columns(problem.clone());
+    let decode = |source: TicketSource, ticket: &[u32]| -> anyhow::Result<DecodedTicket> {
+        let fields = problem
+            .criteria
             .iter()
-            .find(|(_, matching)| matching.len() == 1)
-            .unwrap();
-        let (row_idx, col_idx) = (*current_match.0, current_match.1[0]);
-        correct_criteria_map.insert(row_idx, col_idx);
-        all_criteria_map.remove(&row_idx);
-        idx_to_cols.remove(&col_idx);
+            .enumerate()
+            .map(|(i, rule)| {
+                let column = criteria_to_columns[&i];
+                let value = ticket.get(column).ok_or_else(|| {
+                    let (name, index) = source_name_and_index(source);
+                    anyhow!(
+                        "Ticket {} {} has {} fields, so none in column {} for {}",
+                        name,
+                        index,
+                        ticket.len(),
+                        column,
+                        rule.name
+                    )
+                })?;
+                Ok((rule.name.clone(), *value))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(DecodedTicket {
+            source,
+            valid: check_ticket_validity(&problem.criteria, ticket),
+            fields,
+        })
+    };
+    std::iter::once(decode(TicketSource::Mine, &problem.my_ticket))
+        .chain(
+            problem
+                .nearby_tickets
+                .iter()
+                .enumerate()
+                .map(|(i, ticket)| decode(TicketSource::Nearby(i), ticket)),
+        )
+        .collect()
+}
+
+fn source_name_and_index(source: TicketSource) -> (&'static str, usize) {
+    match source {
+        TicketSource::Mine => ("mine", 0),
+        TicketSource::Nearby(i) => ("nearby", i),
+    }
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn escape_json(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            c if c.is_control() => format!("\\u{:04x}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+// A header row of "source,index,valid" and the field names, then one row per ticket.
+fn tickets_to_csv(tickets: &[DecodedTicket]) -> String {
+    let mut lines = vec![];
+    if let Some(first) = tickets.first() {
+        let names = first.fields.iter().map(|(name, _)| escape_csv(name));
+        lines.push(
+            ["source", "index", "valid"]
+                .iter()
+                .map(|s| s.to_string())
+                .chain(names)
+                .join(","),
+        );
+    }
+    for ticket in tickets {
+        let (source, index) = source_name_and_index(ticket.source);
+        let values = ticket.fields.iter().map(|(_, value)| value.to_string());
+        let row = vec![source.to_string(), index.to_string(), ticket.valid.to_string()];
+        lines.push(row.into_iter().chain(values).join(","));
     }
-    correct_criteria_map
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+// An array of objects, one per line, with the values under "fields" keyed by field name.
+fn tickets_to_json(tickets: &[DecodedTicket]) -> String {
+    let mut objects = tickets.iter().map(|ticket| {
+        let (source, index) = source_name_and_index(ticket.source);
+        let fields = ticket
+            .fields
+            .iter()
+            .map(|(name, value)| format!("\"{}\": {}", escape_json(name), value))
+            .join(", ");
+        format!(
+            "  {{\"source\": \"{}\", \"index\": {}, \"valid\": {}, \"fields\": {{{}}}}}",
+            source, index, ticket.valid, fields
+        )
+    });
+    format!("[\n{}\n]\n", objects.join(",\n"))
 }
 
 fn convert_tickets_to_cols<'a, I: Iterator<Item = &'a Vec<u32>>>(iter: I, len: usize) -> Vec<Vec<u32>> {
@@ -195,6 +466,66 @@ mod tests {
         assert_eq!(check_ticket_validity(&input.criteria, &input.nearby_tickets[3]), false);
     }
 
+    #[test]
+    fn test_ticket_diagnostics() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day16").unwrap());
+        let diagnostics = validate_tickets(&input.criteria, &input.nearby_tickets);
+        let report: String = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            report,
+            "ticket 1, field 1: 4 breaks class (not allowed), row (not allowed), seat (not allowed)\n\
+             ticket 2, field 0: 55 breaks class (not allowed), row (not allowed), seat (not allowed)\n\
+             ticket 3, field 2: 12 breaks class (not allowed), row (not allowed), seat (not allowed)\n"
+        );
+    }
+
+    #[test]
+    fn test_rule_shapes() {
+        let rule: Rule = "arrival track: 1-3 or 7 or 10-20 or not 12-13 or not 15"
+            .parse()
+            .unwrap();
+        assert_eq!(rule.name, "arrival track");
+        assert_eq!(rule.allowed, vec![1..=3, 7..=7, 10..=20]);
+        assert_eq!(rule.excluded, vec![12..=13, 15..=15]);
+        assert_eq!(
+            rule.to_string(),
+            "arrival track: 1-3 or 7 or 10-20 or not 12-13 or not 15"
+        );
+        let passing: Vec<u32> = (0..=21).filter(|value| rule.contains(*value)).collect();
+        assert_eq!(passing, vec![1, 2, 3, 7, 10, 11, 14, 16, 17, 18, 19, 20]);
+        assert_eq!(rule.check(12), Err(Violation::Excluded(12..=13)));
+
+        assert!("no ranges".parse::<Rule>().is_err());
+        assert!("class: 5-1".parse::<Rule>().is_err());
+        assert!("class: 1-x".parse::<Rule>().is_err());
+        assert!("class: not 4".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn test_diagnostics_name_the_broken_exclusion() {
+        let criteria: Vec<Rule> = vec!["class: 1-10 or not 5".parse().unwrap(), "row: 20-30".parse().unwrap()];
+        assert!(validate_ticket(&criteria, &[1, 25]).is_empty());
+        let diagnostics = validate_tickets(&criteria, &[vec![1, 25], vec![25, 5]]);
+        assert_eq!(
+            diagnostics,
+            vec![TicketDiagnostic {
+                ticket: 1,
+                invalid_fields: vec![FieldDiagnostic {
+                    position: 1,
+                    value: 5,
+                    broken_rules: vec![
+                        ("class".to_string(), Violation::Excluded(5..=5)),
+                        ("row".to_string(), Violation::OutsideAllowed),
+                    ],
+                }],
+            }]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "ticket 1, field 1: 5 breaks class (excludes 5), row (not allowed)\n"
+        );
+    }
+
     #[test]
     fn test_determine_criteria_to_columns() {
         let input = parse_input(&fs::read_to_string("./test_inputs/day16_2").unwrap());
@@ -205,6 +536,81 @@ mod tests {
         assert_eq!(result, determine_criteria_to_columns(input));
     }
 
+    #[test]
+    fn test_decoded_tickets() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day16_2").unwrap());
+        let tickets = decode_tickets(input).unwrap();
+        assert_eq!(tickets.len(), 4);
+        assert_eq!(tickets[0].source, TicketSource::Mine);
+        assert_eq!(tickets[0].get("class"), Some(12));
+        assert_eq!(tickets[0].get("row"), Some(11));
+        assert_eq!(tickets[0].get("seat"), Some(13));
+        assert_eq!(tickets[0].get("zone"), None);
+        assert_eq!(tickets[3].source, TicketSource::Nearby(2));
+        assert_eq!(tickets[3].get("row"), Some(5));
+    }
+
+    #[test]
+    fn test_departure_product() {
+        let input = parse_input(
+            "departure row: 0-5 or 8-19\nclass: 0-1 or 4-19\ndeparture seat: 0-13 or 16-19\n\n\
+             your ticket:\n11,12,13\n\n\
+             nearby tickets:\n3,9,18\n15,1,5\n5,14,9",
+        );
+        assert_eq!(part_2(&decode_tickets(input).unwrap()), 11 * 13);
+    }
+
+    #[test]
+    fn test_decoding_short_tickets() {
+        let input = parse_input(
+            "class: 0-1 or 4-19\nrow: 0-5 or 8-19\nseat: 0-13 or 16-19\n\n\
+             your ticket:\n11,12,13\n\n\
+             nearby tickets:\n3,9,18\n15,1,5\n5,14,9\n3,9",
+        );
+        assert_eq!(
+            decode_tickets(input).unwrap_err().to_string(),
+            "Ticket nearby 3 has 2 fields, so none in column 2 for seat"
+        );
+    }
+
+    #[test]
+    fn test_ticket_export() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day16_2").unwrap());
+        let tickets = decode_tickets(input).unwrap();
+        assert_eq!(
+            tickets_to_csv(&tickets),
+            "source,index,valid,class,row,seat\n\
+             mine,0,true,12,11,13\n\
+             nearby,0,true,9,3,18\n\
+             nearby,1,true,1,15,5\n\
+             nearby,2,true,14,5,9\n"
+        );
+        assert_eq!(
+            tickets_to_json(&tickets[..2]),
+            "[\n  \
+             {\"source\": \"mine\", \"index\": 0, \"valid\": true, \"fields\": {\"class\": 12, \"row\": 11, \"seat\": 13}},\n  \
+             {\"source\": \"nearby\", \"index\": 0, \"valid\": true, \"fields\": {\"class\": 9, \"row\": 3, \"seat\": 18}}\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn test_export_escaping() {
+        let tickets = vec![DecodedTicket {
+            source: TicketSource::Nearby(3),
+            valid: false,
+            fields: vec![("a \"quoted\", name".to_string(), 1)],
+        }];
+        assert_eq!(
+            tickets_to_csv(&tickets),
+            "source,index,valid,\"a \"\"quoted\"\", name\"\nnearby,3,false,1\n"
+        );
+        assert_eq!(
+            tickets_to_json(&tickets),
+            "[\n  {\"source\": \"nearby\", \"index\": 3, \"valid\": false, \"fields\": {\"a \\\"quoted\\\", name\": 1}}\n]\n"
+        );
+    }
+
     #[test]
     fn test_convert_tickets_to_cols() {
         let input = vec![vec![1, 2, 3], vec![10, 20, 30], vec![100, 200, 300]];
@@ -216,7 +622,7 @@ mod tests {
     fn test_get_matching_columns_for_criteria() {
         let input = vec![vec![1, 2, 3], vec![20, 30, 40], vec![50, 60, 70]];
         let input: HashMap<usize, Vec<u32>> = input.into_iter().enumerate().collect();
-        let criteria = (1..=3, 50..=70);
+        let criteria: Rule = "test: 1-3 or 50-70".parse().unwrap();
         let correct: HashSet<_> = vec![0, 2].into_iter().collect();
         let result: HashSet<_> = get_matching_columns_for_criteria(&criteria, &input)
             .into_iter()
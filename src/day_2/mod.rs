@@ -1,7 +1,10 @@
 use lazy_static::lazy_static;
+use policy::{Clause, Policy};
 use regex::Regex;
 use std::fs;
 
+mod policy;
+
 struct Entry {
     min: usize,
     max: usize,
@@ -31,27 +34,48 @@ fn line_to_entry(line: &str) -> Option<Entry> {
     })
 }
 
-fn part_1(input: Vec<Entry>) -> usize {
-    input.iter().filter(check_part_1_criteria).count()
+impl Entry {
+    // "1-3 a" as a count of `a`.
+    fn part_1_policy(&self) -> Policy {
+        Policy::new(vec![Clause::Count(self.check, Some(self.min), Some(self.max))])
+    }
+
+    // "1-3 a" as `a` at exactly one of positions 1 and 3.
+    fn part_2_policy(&self) -> Policy {
+        Policy::new(vec![Clause::Xor(self.check, self.min, self.max)])
+    }
 }
 
-fn part_2(input: Vec<Entry>) -> usize {
-    input.iter().filter(check_part_2_criteria).count()
+fn part_1(input: Vec<Entry>) -> usize {
+    input
+        .iter()
+        .filter(|entry| entry.part_1_policy().allows(&entry.password))
+        .count()
 }
 
-#[inline]
-fn check_part_1_criteria(entry: &&Entry) -> bool {
-    let count = entry.password.chars().filter(|&c| c == entry.check).count();
-    (entry.min..=entry.max).contains(&count)
+fn part_2(input: Vec<Entry>) -> usize {
+    input
+        .iter()
+        .filter(|entry| entry.part_2_policy().allows(&entry.password))
+        .count()
 }
 
-#[inline]
-fn check_part_2_criteria(entry: &&Entry) -> bool {
-    let bytes = entry.password.as_bytes();
-    let (idx1, idx2) = (entry.min - 1, entry.max - 1);
-    let (first, second) = (bytes[idx1] as char, bytes[idx2] as char);
-    let (res1, res2) = (first == entry.check, second == entry.check);
-    res1 ^ res2
+// Checks every password against its line's policy, as `line_policy` reads it, and then `policy`.
+fn rejections<'a>(
+    input: &'a [Entry],
+    line_policy: fn(&Entry) -> Policy,
+    policy: &Policy,
+) -> Vec<(&'a str, policy::Violation)> {
+    input
+        .iter()
+        .filter_map(|entry| {
+            line_policy(entry)
+                .and(policy)
+                .check(&entry.password)
+                .err()
+                .map(|violation| (entry.password.as_str(), violation))
+        })
+        .collect()
 }
 
 pub fn solve_part_1() -> Result<(), ()> {
@@ -66,6 +90,29 @@ pub fn solve_part_2() -> Result<(), ()> {
     Ok(())
 }
 
+// Counts the passwords that pass the given part's policy and also `policy`.
+pub fn solve_with_policy(part: usize, policy: &str, log: bool) -> Result<(), ()> {
+    let line_policy = match part {
+        1 => Entry::part_1_policy,
+        2 => Entry::part_2_policy,
+        _ => {
+            println!("There is no part {}", part);
+            return Err(());
+        }
+    };
+    let policy: Policy = policy.parse().map_err(|e| println!("{}", e))?;
+    let input = parse_input(&fs::read_to_string("inputs/day2.txt").unwrap());
+    println!("Checking against part {}'s policy and {}", part, policy);
+    let rejected = rejections(&input, line_policy, &policy);
+    if log {
+        for (password, violation) in rejected.iter() {
+            println!("{} {}", password, violation);
+        }
+    }
+    println!("Solution: {}", input.len() - rejected.len());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +131,31 @@ mod tests {
         let input = parse_input(TEST_INPUT);
         assert_eq!(part_2(input), 1)
     }
+
+    #[test]
+    fn test_rejections() {
+        let input = parse_input(TEST_INPUT);
+        let policy: Policy = "length 5-; forbid e".parse().unwrap();
+        let describe = |rejected: Vec<(&str, policy::Violation)>| -> Vec<String> {
+            rejected
+                .iter()
+                .map(|(password, violation)| format!("{} {}", password, violation))
+                .collect()
+        };
+        assert_eq!(
+            describe(rejections(&input, Entry::part_1_policy, &policy)),
+            vec![
+                "abcde fails \"forbid e\": contains 'e'",
+                "cdefg fails \"count b 1-3\": 'b' appears 0 times",
+            ]
+        );
+        assert_eq!(
+            describe(rejections(&input, Entry::part_2_policy, &policy)),
+            vec![
+                "abcde fails \"forbid e\": contains 'e'",
+                "cdefg fails \"xor b 1 3\": 'b' is at 0 of the positions",
+                "ccccccccc fails \"xor c 2 9\": 'c' is at 2 of the positions",
+            ]
+        );
+    }
 }
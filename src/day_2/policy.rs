@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use std::fmt;
+use std::str::FromStr;
+
+// One rule a password has to follow. Positions count from 1, and ranges include both ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clause {
+    // "count a 1-3": `a` appears between 1 and 3 times. Either end can be left off.
+    Count(char, Option<usize>, Option<usize>),
+    // "xor a 1 3": `a` is at exactly one of the two positions.
+    Xor(char, usize, usize),
+    // "and a 1 3": `a` is at both positions.
+    And(char, usize, usize),
+    // "forbid xyz": none of the characters appear.
+    Forbid(Vec<char>),
+    // "length 8-20": the password is 8 to 20 characters long.
+    Length(Option<usize>, Option<usize>),
+}
+
+// Clauses separated by semicolons, all of which have to hold, e.g. "count a 1-3; forbid xyz".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    clauses: Vec<Clause>,
+}
+
+// The first clause a password broke, and what was wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub clause: Clause,
+    pub reason: String,
+}
+
+fn in_range(value: usize, min: Option<usize>, max: Option<usize>) -> bool {
+    min.unwrap_or(0) <= value && value <= max.unwrap_or(usize::MAX)
+}
+
+fn char_at(password: &str, position: usize) -> Option<char> {
+    password.chars().nth(position - 1)
+}
+
+impl Clause {
+    // Why `password` breaks this clause, if it does.
+    pub fn check(&self, password: &str) -> Option<String> {
+        match *self {
+            Clause::Count(c, min, max) => {
+                let count = password.chars().filter(|&other| other == c).count();
+                if in_range(count, min, max) {
+                    None
+                } else {
+                    Some(format!("'{}' appears {} times", c, count))
+                }
+            }
+            Clause::Xor(c, first, second) | Clause::And(c, first, second) => {
+                let matches = [first, second]
+                    .iter()
+                    .filter(|&&position| char_at(password, position) == Some(c))
+                    .count();
+                match (self, matches) {
+                    (Clause::Xor(..), 1) | (Clause::And(..), 2) => None,
+                    _ => Some(format!("'{}' is at {} of the positions", c, matches)),
+                }
+            }
+            Clause::Forbid(ref forbidden) => password
+                .chars()
+                .find(|c| forbidden.contains(c))
+                .map(|c| format!("contains '{}'", c)),
+            Clause::Length(min, max) => {
+                let length = password.chars().count();
+                if in_range(length, min, max) {
+                    None
+                } else {
+                    Some(format!("is {} characters long", length))
+                }
+            }
+        }
+    }
+}
+
+impl Policy {
+    pub fn new(clauses: Vec<Clause>) -> Policy {
+        Policy { clauses }
+    }
+
+    pub fn check(&self, password: &str) -> Result<(), Violation> {
+        for clause in &self.clauses {
+            if let Some(reason) = clause.check(password) {
+                return Err(Violation {
+                    clause: clause.clone(),
+                    reason,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn allows(&self, password: &str) -> bool {
+        self.check(password).is_ok()
+    }
+
+    // This policy's clauses followed by `other`'s, so a password has to pass both.
+    pub fn and(mut self, other: &Policy) -> Policy {
+        self.clauses.extend(other.clauses.iter().cloned());
+        self
+    }
+}
+
+// "1-3", "1-" or "-3", with a missing end meaning no limit.
+fn parse_range(text: &str) -> Result<(Option<usize>, Option<usize>)> {
+    let (min, max) = text
+        .split_once('-')
+        .ok_or_else(|| anyhow!("Expected a range like \"1-3\", found: {}", text))?;
+    let parse_end = |end: &str| -> Result<Option<usize>> {
+        if end.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(end.parse().map_err(|_| anyhow!("Invalid range: {}", text))?))
+        }
+    };
+    match (parse_end(min)?, parse_end(max)?) {
+        (Some(min), Some(max)) if min > max => Err(anyhow!("Range {} runs backwards", text)),
+        range => Ok(range),
+    }
+}
+
+fn parse_char(text: &str) -> Result<char> {
+    text.chars()
+        .exactly_one()
+        .map_err(|_| anyhow!("Expected a single character, found: {}", text))
+}
+
+fn parse_position(text: &str) -> Result<usize> {
+    match text.parse() {
+        Ok(position) if position > 0 => Ok(position),
+        _ => Err(anyhow!("Positions count from 1, found: {}", text)),
+    }
+}
+
+impl FromStr for Clause {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words[..] {
+            ["count", c, range] => {
+                let (min, max) = parse_range(range)?;
+                Ok(Clause::Count(parse_char(c)?, min, max))
+            }
+            ["xor", c, first, second] => Ok(Clause::Xor(
+                parse_char(c)?,
+                parse_position(first)?,
+                parse_position(second)?,
+            )),
+            ["and", c, first, second] => Ok(Clause::And(
+                parse_char(c)?,
+                parse_position(first)?,
+                parse_position(second)?,
+            )),
+            ["forbid", characters] => Ok(Clause::Forbid(characters.chars().collect())),
+            ["length", range] => {
+                let (min, max) = parse_range(range)?;
+                Ok(Clause::Length(min, max))
+            }
+            _ => Err(anyhow!("Unknown clause: {}", text.trim())),
+        }
+    }
+}
+
+impl FromStr for Policy {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let clauses = text
+            .split(';')
+            .map(str::trim)
+            .filter(|clause| !clause.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Clause>>>()?;
+        if clauses.is_empty() {
+            return Err(anyhow!("A policy needs at least one clause"));
+        }
+        Ok(Policy { clauses })
+    }
+}
+
+fn format_range(min: Option<usize>, max: Option<usize>) -> String {
+    let end = |end: Option<usize>| end.map_or(String::new(), |end| end.to_string());
+    format!("{}-{}", end(min), end(max))
+}
+
+impl fmt::Display for Clause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Clause::Count(c, min, max) => write!(f, "count {} {}", c, format_range(*min, *max)),
+            Clause::Xor(c, first, second) => write!(f, "xor {} {} {}", c, first, second),
+            Clause::And(c, first, second) => write!(f, "and {} {} {}", c, first, second),
+            Clause::Forbid(forbidden) => write!(f, "forbid {}", forbidden.iter().collect::<String>()),
+            Clause::Length(min, max) => write!(f, "length {}", format_range(*min, *max)),
+        }
+    }
+}
+
+impl fmt::Display for Policy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.clauses.iter().join("; "))
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fails \"{}\": {}", self.clause, self.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_policies() {
+        let text = "count a 1-3; xor b 1 3; and c 2 4; forbid xyz; length 8-; count d -2";
+        let policy: Policy = text.parse().unwrap();
+        assert_eq!(
+            policy,
+            Policy::new(vec![
+                Clause::Count('a', Some(1), Some(3)),
+                Clause::Xor('b', 1, 3),
+                Clause::And('c', 2, 4),
+                Clause::Forbid(vec!['x', 'y', 'z']),
+                Clause::Length(Some(8), None),
+                Clause::Count('d', None, Some(2)),
+            ])
+        );
+        assert_eq!(policy.to_string(), text);
+
+        let error = |text: &str| text.parse::<Policy>().unwrap_err().to_string();
+        assert_eq!(error("count a 1-3; sum a 4"), "Unknown clause: sum a 4");
+        assert_eq!(error("xor a 0 3"), "Positions count from 1, found: 0");
+        assert_eq!(error("count ab 1-3"), "Expected a single character, found: ab");
+        assert_eq!(error("length 8"), "Expected a range like \"1-3\", found: 8");
+        assert_eq!(error("length 8-x"), "Invalid range: 8-x");
+        assert_eq!(error("count a 3-1"), "Range 3-1 runs backwards");
+        assert_eq!(error("length 9-2"), "Range 9-2 runs backwards");
+        assert!("count a 2-2; length 0-".parse::<Policy>().is_ok());
+        assert_eq!(error(" ; "), "A policy needs at least one clause");
+    }
+
+    #[test]
+    fn test_checking_passwords() {
+        let policy: Policy = "length 4-8; forbid !?; count a 1-2; xor b 1 3; and c 2 4"
+            .parse()
+            .unwrap();
+        assert!(policy.allows("bcac"));
+        let violation = |password: &str| policy.check(password).unwrap_err().to_string();
+        assert_eq!(violation("bca"), "fails \"length 4-8\": is 3 characters long");
+        assert_eq!(violation("bcac?"), "fails \"forbid !?\": contains '?'");
+        assert_eq!(violation("bcbc"), "fails \"count a 1-2\": 'a' appears 0 times");
+        assert_eq!(violation("bcbca"), "fails \"xor b 1 3\": 'b' is at 2 of the positions");
+        assert_eq!(violation("bxaa"), "fails \"and c 2 4\": 'c' is at 0 of the positions");
+
+        // Positions past the end of the password just don't match.
+        let policy: Policy = "xor z 2 30".parse().unwrap();
+        assert!(policy.allows("az"));
+        assert!(!policy.allows("a"));
+    }
+}
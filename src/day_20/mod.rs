@@ -1,4 +1,8 @@
-use hashbrown::HashMap;
+mod orientation;
+
+use crate::day_20::orientation::{Orientation, Transform};
+use anyhow::{anyhow, Context};
+use hashbrown::{HashMap, HashSet};
 use itertools::Itertools;
 use std::fmt::Display;
 use std::{fmt, fs};
@@ -11,7 +15,30 @@ pub fn solve_part_1() -> Result<(), ()> {
 
 pub fn solve_part_2() -> Result<(), ()> {
     let input = parse_input(&fs::read_to_string("./inputs/day20.txt").unwrap());
-    println!("{:?}", part_2(input, 12));
+    let pieces = solve_layout(&input, 12);
+    println!("{:?}", part_2(input, pieces));
+    Ok(())
+}
+
+// Assembling the full image is slow, so part 2 can read the layout from a file saved by an earlier
+// run instead of solving it, and save the layout it used.
+pub fn solve_with_layout(part: usize, layout_in: Option<&str>, layout_out: Option<&str>) -> Result<(), ()> {
+    if part != 2 {
+        println!("Only part 2 assembles a layout, not part {}", part);
+        return Err(());
+    }
+    let input = parse_input(&fs::read_to_string("./inputs/day20.txt").unwrap());
+    let pieces = match layout_in {
+        Some(path) => fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read the layout from {}", path))
+            .and_then(|layout| import_layout(&layout, &input.tiles))
+            .map_err(|e| println!("{:#}", e))?,
+        None => solve_layout(&input, 12),
+    };
+    if let Some(path) = layout_out {
+        fs::write(path, export_layout(&pieces)).map_err(|e| println!("Couldn't save the layout to {}: {}", path, e))?;
+    }
+    println!("{:?}", part_2(input, pieces));
     Ok(())
 }
 
@@ -20,25 +47,19 @@ fn part_1(input: Problem) -> u64 {
     corners.iter().map(|(_, t)| t.id as u64).product::<u64>()
 }
 
-fn part_2(input: Problem, grid_size: usize) -> usize {
-    let map_fragments = input.map_fragments.clone();
-    let solved_grid = solve_complete_grid(input, grid_size).unwrap();
-    let resulting_pieces: HashMap<(usize, usize), Tile> = solved_grid
-        .into_iter()
-        .enumerate()
-        .flat_map(|(y, row)| {
-            row.into_iter()
-                .enumerate()
-                .map(move |(x, tile)| ((y, x), tile.unwrap()))
-        })
-        .collect();
-    let resulting_map = build_map_from_original(resulting_pieces, map_fragments, grid_size);
+fn solve_layout(input: &Problem, grid_size: usize) -> Pieces {
+    pieces_from_grid(solve_complete_grid(input.clone(), grid_size).unwrap())
+}
+
+fn part_2(input: Problem, pieces: Pieces) -> usize {
+    let grid_size = grid_size_of_pieces(&pieces);
+    let resulting_map = build_map_from_original(pieces, input.map_fragments, grid_size);
     let big_fragment = MapFragment {
         id: 1000,
         fragment: resulting_map,
     };
-    let (rotation, flip) = find_rotation_for_nessies(&big_fragment);
-    let nessie_count = count_nessies_in_map(&big_fragment.rotated(rotation).flipped(flip));
+    let orientation = find_orientation_for_nessies(&big_fragment);
+    let nessie_count = count_nessies_in_map(&big_fragment.transformed(orientation));
     let choppy_count = big_fragment
         .fragment
         .iter()
@@ -49,14 +70,14 @@ fn part_2(input: Problem, grid_size: usize) -> usize {
 }
 
 fn build_map_from_original(
-    resulting_pieces: HashMap<(usize, usize), Tile>,
+    resulting_pieces: Pieces,
     mut map_fragments: HashMap<u32, MapFragment>,
     grid_size: usize,
 ) -> Vec<Vec<char>> {
     let mut full_corrected_map: Vec<Vec<char>> = vec![vec![]; grid_size * 8]; // There will be 8 lines for each part.
     for ((y, _), tile) in resulting_pieces.iter().sorted_by_key(|(k, _)| *k) {
         let map_fragment = map_fragments.remove(&tile.id).unwrap();
-        let mut corrected_map_fragment = map_fragment.rotated(tile.rotated).flipped(tile.flipped);
+        let mut corrected_map_fragment = map_fragment.transformed(tile.orientation);
         for i in 0..8 {
             full_corrected_map[y * 8 + i].append(&mut corrected_map_fragment.fragment[i]);
         }
@@ -65,6 +86,77 @@ fn build_map_from_original(
 }
 
 type Grid = Vec<Vec<Option<Tile>>>;
+type Pieces = HashMap<(usize, usize), Tile>;
+
+fn pieces_from_grid(grid: Grid) -> Pieces {
+    grid.into_iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.into_iter()
+                .enumerate()
+                .map(move |(x, tile)| ((y, x), tile.unwrap()))
+        })
+        .collect()
+}
+
+fn grid_size_of_pieces(pieces: &Pieces) -> usize {
+    pieces.keys().map(|(y, _)| y + 1).max().unwrap_or(0)
+}
+
+// One line per placed tile: "<y> <x> <tile id> <orientation>".
+fn export_layout(pieces: &Pieces) -> String {
+    pieces
+        .iter()
+        .sorted_by_key(|(k, _)| *k)
+        .map(|((y, x), tile)| format!("{} {} {} {}", y, x, tile.id, tile.orientation))
+        .join("\n")
+}
+
+fn import_layout(layout: &str, tiles: &HashMap<u32, Tile>) -> anyhow::Result<Pieces> {
+    let mut pieces = Pieces::new();
+    let mut used_ids = HashSet::new();
+    for line in layout.lines().filter(|line| !line.trim().is_empty()) {
+        let (y, x, id, orientation) = line
+            .split_whitespace()
+            .collect_tuple()
+            .ok_or_else(|| anyhow!("Expected \"<y> <x> <tile id> <orientation>\", found: {}", line))?;
+        let position = (
+            y.parse().with_context(|| format!("Bad row in: {}", line))?,
+            x.parse().with_context(|| format!("Bad column in: {}", line))?,
+        );
+        let id: u32 = id.parse().with_context(|| format!("Bad tile id in: {}", line))?;
+        let orientation: Orientation = orientation.parse()?;
+        let tile = tiles
+            .get(&id)
+            .ok_or_else(|| anyhow!("Tile {} is not in the input", id))?;
+        if !used_ids.insert(id) {
+            return Err(anyhow!("Tile {} is placed more than once", id));
+        }
+        if pieces.insert(position, tile.transformed(orientation)).is_some() {
+            return Err(anyhow!("Position {:?} is filled more than once", position));
+        }
+    }
+
+    if pieces.len() != tiles.len() {
+        return Err(anyhow!(
+            "The layout places {} of the {} tiles",
+            pieces.len(),
+            tiles.len()
+        ));
+    }
+    let grid_size = grid_size_of_pieces(&pieces);
+    if pieces.len() != grid_size * grid_size || pieces.keys().any(|(_, x)| *x >= grid_size) {
+        return Err(anyhow!("The layout doesn't fill a square grid"));
+    }
+    let mut grid: Grid = vec![vec![None; grid_size]; grid_size];
+    for ((y, x), tile) in pieces.iter() {
+        grid[*y][*x] = Some(tile.clone());
+    }
+    if !check_valid(&grid) {
+        return Err(anyhow!("The tiles in the layout don't line up"));
+    }
+    Ok(pieces)
+}
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Tile {
@@ -73,48 +165,16 @@ pub struct Tile {
     right: u32,
     bottom: u32,
     left: u32,
-    rotated: usize,
-    flipped: Flip,
+    orientation: Orientation,
 }
 
 impl Tile {
-    fn rotated(&self, rotation: usize) -> Tile {
-        (0..rotation).fold(self.clone(), |acc, _| acc.rotate())
-    }
-
-    fn rotate(&self) -> Tile {
+    // Edges are stored as they read in the current orientation, so transforming
+    // a tile moves its edges and records the new overall orientation together.
+    fn transformed(&self, orientation: Orientation) -> Tile {
         Tile {
-            id: self.id,
-            right: self.top,
-            bottom: Tile::reversed(self.right),
-            left: self.bottom,
-            top: Tile::reversed(self.left),
-            rotated: (self.rotated + 1) % 4,
-            flipped: self.flipped,
-        }
-    }
-
-    fn flipped(&self, flip: Flip) -> Tile {
-        match flip {
-            Flip::Zero => self.clone(),
-            Flip::Y => Tile {
-                id: self.id,
-                top: self.bottom,
-                bottom: self.top,
-                right: Tile::reversed(self.right),
-                left: Tile::reversed(self.left),
-                flipped: Flip::Y,
-                rotated: self.rotated,
-            },
-            Flip::X => Tile {
-                id: self.id,
-                right: self.left,
-                left: self.right,
-                top: Tile::reversed(self.top),
-                bottom: Tile::reversed(self.bottom),
-                flipped: Flip::X,
-                rotated: self.rotated,
-            },
+            orientation: self.orientation.then(orientation),
+            ..orientation.apply(self)
         }
     }
 
@@ -143,13 +203,29 @@ impl Display for Tile {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum Flip {
-    Zero,
-    Y,
-    X,
+impl Transform for Tile {
+    fn rotated_clockwise(&self) -> Tile {
+        Tile {
+            right: self.top,
+            bottom: Tile::reversed(self.right),
+            left: self.bottom,
+            top: Tile::reversed(self.left),
+            ..self.clone()
+        }
+    }
+
+    fn mirrored(&self) -> Tile {
+        Tile {
+            right: self.left,
+            left: self.right,
+            top: Tile::reversed(self.top),
+            bottom: Tile::reversed(self.bottom),
+            ..self.clone()
+        }
+    }
 }
 
+#[derive(Clone)]
 struct Problem {
     tiles: HashMap<u32, Tile>,
     map_fragments: HashMap<u32, MapFragment>,
@@ -198,8 +274,7 @@ fn tile_from_tile_description(input: &str) -> Tile {
         right,
         bottom,
         left,
-        flipped: Flip::Zero,
-        rotated: 0,
+        orientation: Orientation::IDENTITY,
     }
 }
 
@@ -209,60 +284,11 @@ struct MapFragment {
     fragment: Vec<Vec<char>>,
 }
 
-fn rotate_vec_of_ch(vec_of_ch: Vec<Vec<char>>) -> Vec<Vec<char>> {
-    let y_size = vec_of_ch.len();
-    let x_size = vec_of_ch[0].len();
-    let mut new = vec![vec!['0'; x_size]; y_size];
-    for (y, row) in vec_of_ch.iter().enumerate() {
-        for (x, c) in row.iter().enumerate() {
-            new[x][y_size - 1 - y] = *c;
-        }
-    }
-    new
-}
-
-fn flip_vec_of_ch(vec_of_ch: Vec<Vec<char>>, flip: Flip) -> Vec<Vec<char>> {
-    match flip {
-        Flip::Zero => vec_of_ch,
-        Flip::Y => {
-            let mut new = vec![];
-            for i in (0..vec_of_ch.len()).rev() {
-                new.push(vec_of_ch[i].clone())
-            }
-            new
-        }
-        Flip::X => {
-            let y_size = vec_of_ch.len();
-            let x_size = vec_of_ch[0].len();
-            let mut new = vec![vec!['0'; x_size]; y_size];
-            for y in 0..y_size {
-                for (i, x) in (0..x_size).rev().enumerate() {
-                    new[y][i] = vec_of_ch[y][x];
-                }
-            }
-            new
-        }
-    }
-}
-
 impl MapFragment {
-    fn rotate(&self) -> MapFragment {
-        let new = rotate_vec_of_ch(self.fragment.clone());
-        MapFragment {
-            id: self.id,
-            fragment: new,
-        }
-    }
-
-    fn rotated(&self, rotation: usize) -> MapFragment {
-        (0..rotation).fold(self.clone(), |acc, _| acc.rotate())
-    }
-
-    fn flipped(&self, flip: Flip) -> MapFragment {
-        let new = flip_vec_of_ch(self.fragment.clone(), flip);
+    fn transformed(&self, orientation: Orientation) -> MapFragment {
         MapFragment {
             id: self.id,
-            fragment: new,
+            fragment: orientation.apply(&self.fragment),
         }
     }
 }
@@ -338,18 +364,16 @@ fn solve_grid(tiles: HashMap<u32, Tile>, grid: Grid) -> Option<Grid> {
         println!("I've moved onto ({}, {})", y, x);
     }
     for (_, tile) in tiles.iter().sorted_by_key(|(k, _)| *k) {
-        for rotation in 0..4 {
-            for flip in [Flip::Zero, Flip::Y, Flip::X].iter() {
-                let tile = tile.rotated(rotation).flipped(*flip);
-                let tile_id = tile.id;
-                let mut new_grid = grid.clone();
-                new_grid[y][x] = Some(tile);
-                if check_valid(&new_grid) {
-                    let mut new_tiles = tiles.clone();
-                    new_tiles.remove(&tile_id);
-                    if let Some(grid) = solve_grid(new_tiles, new_grid) {
-                        return Some(grid);
-                    }
+        for orientation in Orientation::all() {
+            let tile = tile.transformed(orientation);
+            let tile_id = tile.id;
+            let mut new_grid = grid.clone();
+            new_grid[y][x] = Some(tile);
+            if check_valid(&new_grid) {
+                let mut new_tiles = tiles.clone();
+                new_tiles.remove(&tile_id);
+                if let Some(grid) = solve_grid(new_tiles, new_grid) {
+                    return Some(grid);
                 }
             }
         }
@@ -376,25 +400,23 @@ fn solve_border(corners: HashMap<u32, Tile>, edges: HashMap<u32, Tile>, grid: Gr
     let to_choose_from = if is_corner { &corners } else { &edges };
 
     for (_, tile) in to_choose_from.clone().iter().sorted_by_key(|(k, _)| *k) {
-        for rotation in 0..4 {
-            for flip in [Flip::Zero, Flip::Y, Flip::X].iter() {
-                let tile = tile.rotated(rotation).flipped(*flip);
-                let tile_id = tile.id;
-                let mut new_grid = grid.clone();
-                new_grid[y][x] = Some(tile);
-                if check_valid(&new_grid) {
-                    let mut chosen_tile_set = (*to_choose_from).clone();
-                    chosen_tile_set.remove(&tile_id);
-                    let (corners, edges) = {
-                        if is_corner {
-                            (chosen_tile_set, edges.clone())
-                        } else {
-                            (corners.clone(), chosen_tile_set)
-                        }
-                    };
-                    if let Some(grid) = solve_border(corners, edges, new_grid) {
-                        return Some(grid);
+        for orientation in Orientation::all() {
+            let tile = tile.transformed(orientation);
+            let tile_id = tile.id;
+            let mut new_grid = grid.clone();
+            new_grid[y][x] = Some(tile);
+            if check_valid(&new_grid) {
+                let mut chosen_tile_set = (*to_choose_from).clone();
+                chosen_tile_set.remove(&tile_id);
+                let (corners, edges) = {
+                    if is_corner {
+                        (chosen_tile_set, edges.clone())
+                    } else {
+                        (corners.clone(), chosen_tile_set)
                     }
+                };
+                if let Some(grid) = solve_border(corners, edges, new_grid) {
+                    return Some(grid);
                 }
             }
         }
@@ -547,13 +569,10 @@ fn scan_map_for_nessies(map: MapFragment) -> bool {
     false
 }
 
-fn find_rotation_for_nessies(map: &MapFragment) -> (usize, Flip) {
-    for rotation in 0..4 {
-        for flip in [Flip::Zero, Flip::Y, Flip::X].iter() {
-            let transformed = map.rotated(rotation).flipped(*flip);
-            if scan_map_for_nessies(transformed) {
-                return (rotation, *flip);
-            }
+fn find_orientation_for_nessies(map: &MapFragment) -> Orientation {
+    for orientation in Orientation::all() {
+        if scan_map_for_nessies(map.transformed(orientation)) {
+            return orientation;
         }
     }
     panic!("No nessies anywhere to be found");
@@ -577,6 +596,19 @@ fn count_nessies_in_map(map: &MapFragment) -> usize {
 mod tests {
     use super::*;
     use itertools::Itertools;
+    use num::Integer;
+
+    const SINGLE_TILE: &str = "Tile 42:\n\
+                               ##..#.#..#\n\
+                               #.........\n\
+                               ..#......#\n\
+                               #........#\n\
+                               ......#...\n\
+                               #.........\n\
+                               ...#.....#\n\
+                               #.........\n\
+                               .........#\n\
+                               ###.#..#..";
 
     #[test]
     #[ignore]
@@ -592,45 +624,65 @@ mod tests {
     fn test_rotated_and_flipped() {
         let input = parse_input(&fs::read_to_string("./test_inputs/day20").unwrap());
         let tile = input.tiles.get(&1171).unwrap();
-        let rotated = tile.rotated(0);
+        let rotated = tile.transformed(Orientation::IDENTITY);
         assert_eq!(*tile, rotated);
-        let rotated = tile.rotated(1);
+        let rotated = tile.transformed(Orientation::new(1, false));
         let expected = Tile {
             id: 1171,
             top: Tile::reversed(tile.left),
             right: tile.top,
             bottom: Tile::reversed(tile.right),
             left: tile.bottom,
-            rotated: 1,
-            flipped: Flip::Zero,
+            orientation: Orientation::new(1, false),
         };
         assert_eq!(expected, rotated);
 
-        let rotated = tile.rotated(3);
+        let rotated = tile.transformed(Orientation::new(3, false));
         let expected = Tile {
             id: 1171,
             left: Tile::reversed(tile.top),
             bottom: tile.left,
             top: tile.right,
             right: Tile::reversed(tile.bottom),
-            rotated: 3,
-            flipped: Flip::Zero,
+            orientation: Orientation::new(3, false),
         };
         assert_eq!(expected, rotated);
 
-        let rotated_and_flipped = tile.rotated(1).flipped(Flip::Y);
+        let rotated_and_flipped = tile
+            .transformed(Orientation::new(1, false))
+            .transformed(Orientation::new(2, true));
         let expected = Tile {
             id: 1171,
             left: Tile::reversed(tile.bottom),
             bottom: Tile::reversed(tile.left),
             top: Tile::reversed(tile.right),
             right: Tile::reversed(tile.top),
-            rotated: 1,
-            flipped: Flip::Y,
+            orientation: Orientation::new(3, true),
         };
         assert_eq!(expected, rotated_and_flipped);
     }
 
+    #[test]
+    fn test_tile_edges_follow_the_grid() {
+        let tile = tile_from_tile_description(SINGLE_TILE);
+        let grid: Vec<Vec<char>> = SINGLE_TILE
+            .split('\n')
+            .skip(1)
+            .map(|row| row.chars().collect())
+            .collect();
+        for orientation in Orientation::all() {
+            let transformed_grid = orientation.apply(&grid);
+            let transformed_description = std::iter::once("Tile 42:".to_string())
+                .chain(transformed_grid.iter().map(|row| row.iter().collect()))
+                .join("\n");
+            let expected = Tile {
+                orientation,
+                ..tile_from_tile_description(&transformed_description)
+            };
+            assert_eq!(tile.transformed(orientation), expected);
+        }
+    }
+
     #[test]
     fn test_bit_flippin() {
         assert_eq!(0b1000000000, Tile::reversed(0b0000000001));
@@ -651,21 +703,21 @@ mod tests {
         let mut correct_board: Grid = vec![];
         assert!(check_valid(&correct_board));
         correct_board.push(vec![
-            tiles.get(&1951).map(|t| t.flipped(Flip::Y)),
-            tiles.get(&2311).map(|t| t.flipped(Flip::Y)),
+            tiles.get(&1951).map(|t| t.transformed(Orientation::new(2, true))),
+            tiles.get(&2311).map(|t| t.transformed(Orientation::new(2, true))),
             tiles.get(&3079).cloned(),
         ]);
         assert!(check_valid(&correct_board));
         correct_board.push(vec![
-            tiles.get(&2729).map(|t| t.flipped(Flip::Y)),
-            tiles.get(&1427).map(|t| t.flipped(Flip::Y)),
-            tiles.get(&2473).map(|t| t.rotated(3).flipped(Flip::X)),
+            tiles.get(&2729).map(|t| t.transformed(Orientation::new(2, true))),
+            tiles.get(&1427).map(|t| t.transformed(Orientation::new(2, true))),
+            tiles.get(&2473).map(|t| t.transformed(Orientation::new(3, true))),
         ]);
         assert!(check_valid(&correct_board));
         correct_board.push(vec![
-            tiles.get(&2971).map(|t| t.flipped(Flip::Y)),
-            tiles.get(&1489).map(|t| t.flipped(Flip::Y)),
-            tiles.get(&1171).map(|t| t.flipped(Flip::X)),
+            tiles.get(&2971).map(|t| t.transformed(Orientation::new(2, true))),
+            tiles.get(&1489).map(|t| t.transformed(Orientation::new(2, true))),
+            tiles.get(&1171).map(|t| t.transformed(Orientation::new(0, true))),
         ]);
         assert!(check_valid(&correct_board))
     }
@@ -681,18 +733,9 @@ mod tests {
             .inspect(|item| println!("{}", item.as_ref().unwrap().id))
             .map(|tile| (tile.as_ref().unwrap().id, tile.unwrap()))
             .collect();
-        assert_eq!(
-            (result.get(&3079).unwrap().rotated, result.get(&3079).unwrap().flipped),
-            (2, Flip::Zero)
-        );
-        assert_eq!(
-            (result.get(&2473).unwrap().rotated, result.get(&2473).unwrap().flipped),
-            (1, Flip::X)
-        );
-        assert_eq!(
-            (result.get(&2971).unwrap().rotated, result.get(&2971).unwrap().flipped),
-            (0, Flip::X)
-        );
+        assert_eq!(result.get(&3079).unwrap().orientation, Orientation::new(3, true));
+        assert_eq!(result.get(&2473).unwrap().orientation, Orientation::new(0, false));
+        assert_eq!(result.get(&2971).unwrap().orientation, Orientation::new(3, false));
     }
 
     #[test]
@@ -773,6 +816,79 @@ mod tests {
     #[test]
     fn determine_choppiness() {
         let input = parse_input(&fs::read_to_string("./test_inputs/day20").unwrap());
-        assert_eq!(part_2(input, 3), 273);
+        let pieces = solve_layout(&input, 3);
+        assert_eq!(part_2(input, pieces), 273);
+    }
+
+    // A 3 by 3 grid of tiles cut from a 28 by 28 image, overlapping by a row or column so that
+    // their edges line up. Each tile's id is its row and then its column, counting from 1. Once the
+    // borders are dropped, the picture has one sea monster, 2 rows and columns in from its corner.
+    fn three_by_three_tiles() -> String {
+        let image = |y: usize, x: usize| {
+            let in_monster = !Integer::is_multiple_of(&y, &9)
+                && !Integer::is_multiple_of(&x, &9)
+                && NESSIE_OFFSETS.contains(&((y / 9 * 8 + y % 9).wrapping_sub(3), (x / 9 * 8 + x % 9).wrapping_sub(3)));
+            if in_monster || ((y * 31 + x * 17) ^ (y * x)) % 7 < 3 {
+                '#'
+            } else {
+                '.'
+            }
+        };
+        (0..3)
+            .cartesian_product(0..3)
+            .map(|(tile_y, tile_x)| {
+                let mut rows = (0..10).map(|y| {
+                    (0..10)
+                        .map(|x| image(tile_y * 9 + y, tile_x * 9 + x))
+                        .collect::<String>()
+                });
+                format!("Tile {}{}:\n{}", tile_y + 1, tile_x + 1, rows.join("\n"))
+            })
+            .join("\n\n")
+    }
+
+    #[test]
+    fn test_layout_export_and_import() {
+        let text = three_by_three_tiles();
+        let input = parse_input(&text);
+        let layout = (0..3)
+            .cartesian_product(0..3)
+            .map(|(y, x)| format!("{} {} {}{} r0", y, x, y + 1, x + 1))
+            .join("\n");
+        let pieces = import_layout(&layout, &input.tiles).unwrap();
+        assert_eq!(export_layout(&pieces), layout);
+        assert_eq!(import_layout(&export_layout(&pieces), &input.tiles).unwrap(), pieces);
+
+        // The roughness is every '#' inside the tiles' borders, less the sea monster's.
+        let inside: usize = text
+            .split("\n\n")
+            .flat_map(|tile| tile.lines().skip(2).take(8))
+            .map(|row| row[1..9].matches('#').count())
+            .sum();
+        assert_eq!(part_2(input.clone(), pieces), inside - NESSIE_OFFSETS.len());
+
+        // Layouts saved for some other input.
+        let error = |layout: &str| import_layout(layout, &input.tiles).unwrap_err().to_string();
+        assert_eq!(
+            error(&layout.replacen("0 0 11", "0 0 12", 1).replacen("0 1 12", "0 1 11", 1)),
+            "The tiles in the layout don't line up"
+        );
+        assert_eq!(
+            error("0 0 11 r0\n0 1 12 r0\n1 0 21 r0\n1 1 22 r0"),
+            "The layout places 4 of the 9 tiles"
+        );
+    }
+
+    #[test]
+    fn test_import_layout_rejects_bad_layouts() {
+        let input = parse_input(SINGLE_TILE);
+        let imported = import_layout("0 0 42 r1f\n", &input.tiles).unwrap();
+        assert_eq!(imported.get(&(0, 0)).unwrap().orientation, Orientation::new(1, true));
+        assert!(import_layout("0 0 42", &input.tiles).is_err());
+        assert!(import_layout("0 0 43 r0", &input.tiles).is_err());
+        assert!(import_layout("0 0 42 r4", &input.tiles).is_err());
+        assert!(import_layout("0 a 42 r0", &input.tiles).is_err());
+        assert!(import_layout("0 1 42 r0", &input.tiles).is_err());
+        assert!(import_layout("0 0 42 r0\n0 0 42 r1", &input.tiles).is_err());
     }
 }
@@ -0,0 +1,164 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::str::FromStr;
+
+// The eight symmetries of a square (the dihedral group D4). An orientation is
+// `rotation` clockwise quarter turns followed, if `flipped`, by a mirror that
+// swaps the left and right sides.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Orientation {
+    rotation: u8,
+    flipped: bool,
+}
+
+pub trait Transform: Clone {
+    fn rotated_clockwise(&self) -> Self;
+    fn mirrored(&self) -> Self;
+}
+
+impl Orientation {
+    pub const IDENTITY: Orientation = Orientation {
+        rotation: 0,
+        flipped: false,
+    };
+
+    pub fn new(rotation: usize, flipped: bool) -> Orientation {
+        Orientation {
+            rotation: (rotation % 4) as u8,
+            flipped,
+        }
+    }
+
+    pub fn all() -> impl Iterator<Item = Orientation> {
+        [false, true]
+            .iter()
+            .flat_map(|&flipped| (0..4).map(move |rotation| Orientation::new(rotation, flipped)))
+    }
+
+    pub fn rotation(self) -> usize {
+        self.rotation as usize
+    }
+
+    // The orientation equivalent to applying `self` and then `next`.
+    pub fn then(self, next: Orientation) -> Orientation {
+        // A mirror reverses the direction of any rotation that follows it.
+        let next_rotation = if self.flipped {
+            4 - next.rotation()
+        } else {
+            next.rotation()
+        };
+        Orientation::new(self.rotation() + next_rotation, self.flipped ^ next.flipped)
+    }
+
+    #[allow(dead_code)]
+    pub fn inverse(self) -> Orientation {
+        if self.flipped {
+            // Every reflection undoes itself.
+            self
+        } else {
+            Orientation::new(4 - self.rotation(), false)
+        }
+    }
+
+    pub fn apply<T: Transform>(self, item: &T) -> T {
+        let rotated = (0..self.rotation).fold(item.clone(), |acc, _| acc.rotated_clockwise());
+        if self.flipped {
+            rotated.mirrored()
+        } else {
+            rotated
+        }
+    }
+}
+
+// Written as the number of quarter turns, with an `f` suffix when flipped: "r0", "r3f".
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "r{}{}", self.rotation, if self.flipped { "f" } else { "" })
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let (rotation, flipped) = match input.strip_suffix('f') {
+            Some(rotation) => (rotation, true),
+            None => (input, false),
+        };
+        match rotation.strip_prefix('r').and_then(|r| r.parse::<usize>().ok()) {
+            Some(rotation) if rotation < 4 => Ok(Orientation::new(rotation, flipped)),
+            _ => Err(anyhow!("Invalid orientation: {}", input)),
+        }
+    }
+}
+
+impl<T: Clone> Transform for Vec<Vec<T>> {
+    fn rotated_clockwise(&self) -> Self {
+        let y_size = self.len();
+        let x_size = self.first().map_or(0, |row| row.len());
+        (0..x_size)
+            .map(|x| (0..y_size).rev().map(|y| self[y][x].clone()).collect())
+            .collect()
+    }
+
+    fn mirrored(&self) -> Self {
+        self.iter().map(|row| row.iter().rev().cloned().collect()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Vec<Vec<char>> {
+        rows.iter().map(|row| row.chars().collect()).collect()
+    }
+
+    #[test]
+    fn test_applying_to_grids() {
+        let original = grid(&["ab", "cd"]);
+        assert_eq!(Orientation::IDENTITY.apply(&original), original);
+        assert_eq!(Orientation::new(1, false).apply(&original), grid(&["ca", "db"]));
+        assert_eq!(Orientation::new(2, false).apply(&original), grid(&["dc", "ba"]));
+        assert_eq!(Orientation::new(0, true).apply(&original), grid(&["ba", "dc"]));
+        assert_eq!(Orientation::new(1, true).apply(&original), grid(&["ac", "bd"]));
+        assert_eq!(Orientation::new(2, true).apply(&original), grid(&["cd", "ab"]));
+    }
+
+    #[test]
+    fn test_orientation_text_round_trip() {
+        for orientation in Orientation::all() {
+            assert_eq!(orientation.to_string().parse::<Orientation>().unwrap(), orientation);
+        }
+        assert_eq!(Orientation::new(3, true).to_string(), "r3f");
+        assert!("r4".parse::<Orientation>().is_err());
+        assert!("f".parse::<Orientation>().is_err());
+        assert!("1".parse::<Orientation>().is_err());
+    }
+
+    #[test]
+    fn test_all_orientations_are_distinct() {
+        let original = grid(&["ab.", "...", "..."]);
+        let results: Vec<_> = Orientation::all().map(|o| o.apply(&original)).collect();
+        assert_eq!(results.len(), 8);
+        for (i, a) in results.iter().enumerate() {
+            assert!(results.iter().skip(i + 1).all(|b| a != b));
+        }
+    }
+
+    #[test]
+    fn test_composition_and_inverse() {
+        let original = grid(&["abc", "def", "ghi"]);
+        for first in Orientation::all() {
+            assert_eq!(first.then(first.inverse()), Orientation::IDENTITY);
+            assert_eq!(first.inverse().then(first), Orientation::IDENTITY);
+            assert_eq!(first.inverse().apply(&first.apply(&original)), original);
+            for second in Orientation::all() {
+                assert_eq!(
+                    first.then(second).apply(&original),
+                    second.apply(&first.apply(&original))
+                );
+            }
+        }
+    }
+}
@@ -1,7 +1,9 @@
-use hashbrown::HashMap;
+use crate::matching::{self, Matcher};
 use hashbrown::HashSet;
 use itertools::Itertools;
-use std::fs;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::{fmt, fs};
 
 pub fn solve_part_1() -> Result<(), ()> {
     let input = parse_input(&fs::read_to_string("./inputs/day_21.txt").unwrap());
@@ -10,34 +12,45 @@ pub fn solve_part_1() -> Result<(), ()> {
 }
 
 fn part_1(input: Input) -> usize {
-    let allergens = determine_allergens(input.allergens_to_ingredients, HashMap::new());
-    let allergens: Vec<String> = allergens.keys().cloned().collect();
+    let solution = solve_allergens(&input.foods);
+    let possible_allergens = solution.possible_allergen_ingredients();
     input
         .list_of_individual_ingredients
         .iter()
-        .filter(|ingredient| !allergens.contains(ingredient))
+        .filter(|ingredient| !possible_allergens.contains(ingredient.as_str()))
         .count()
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
     let input = parse_input(&fs::read_to_string("./inputs/day_21.txt").unwrap());
+    for deduction in solve_allergens(&input.foods).deductions {
+        println!("{}", deduction);
+    }
     println!("{}", part_2(input));
     Ok(())
 }
 
 fn part_2(input: Input) -> String {
-    let allergens = determine_allergens(input.allergens_to_ingredients, HashMap::new());
-    allergens
-        .iter()
-        .sorted_by_key(|(_, v)| <&std::string::String>::clone(v))
-        .map(|(k, _)| k.clone())
-        .join(",")
+    match solve_allergens(&input.foods).outcome {
+        Outcome::Unique(assignment) => assignment.values().join(","),
+        Outcome::Multiple(assignments) => panic!(
+            "The foods allow more than one allergen assignment, such as {:?}",
+            &assignments[..2]
+        ),
+        Outcome::Impossible(allergens) => panic!("Too few ingredients can contain all of: {:?}", allergens),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Food {
+    ingredients: HashSet<String>,
+    allergens: Vec<String>,
 }
 
 fn parse_input(input: &str) -> Input {
-    let mut allergens_to_ingredients = HashMap::new();
+    let mut foods = vec![];
     let mut list_of_individual_ingredients = vec![];
-    for (allergens_list, ingredients_set) in input.split('\n').map(|line| {
+    for line in input.split('\n') {
         let ingredients: String = line.chars().take_while(|c| *c != '(').collect();
         let allergens: String = line
             .chars()
@@ -53,55 +66,113 @@ fn parse_input(input: &str) -> Input {
             .skip(1)
             .map(|value| value.trim_matches(',').to_string())
             .collect();
-        (allergens_list, ingredients_set)
-    }) {
-        for allergen in allergens_list {
-            let entry = allergens_to_ingredients.entry(allergen).or_insert(vec![]);
-            entry.push(ingredients_set.clone());
-        }
+        foods.push(Food {
+            ingredients: ingredients_set,
+            allergens: allergens_list,
+        });
     }
     Input {
-        allergens_to_ingredients,
+        foods,
         list_of_individual_ingredients,
     }
 }
 
-fn remove_allergen_ingredient_from_map(map: &mut HashMap<String, Vec<HashSet<String>>>, allergen_ingredient: &str) {
-    for (_, v) in map.iter_mut() {
-        for set in v.iter_mut() {
-            set.remove(allergen_ingredient);
+// Allergen to the ingredient containing it.
+type Outcome = matching::Outcome<String, String>;
+
+#[derive(Debug, PartialEq, Eq)]
+struct Deduction {
+    allergen: String,
+    ingredient: String,
+    // Indices of the foods listing the allergen, whose shared ingredients were the starting candidates.
+    foods: Vec<usize>,
+    // Earlier deductions that ruled out the other shared ingredients, as (allergen, ingredient).
+    ruled_out: Vec<(String, String)>,
+}
+
+impl Display for Deduction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} contains {}: foods {} share it",
+            self.ingredient,
+            self.allergen,
+            self.foods.iter().map(|i| i + 1).join(", ")
+        )?;
+        if !self.ruled_out.is_empty() {
+            write!(
+                f,
+                ", and every other shared ingredient is taken ({})",
+                self.ruled_out
+                    .iter()
+                    .map(|(allergen, ingredient)| format!("{} contains {}", ingredient, allergen))
+                    .join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct AllergenSolution {
+    outcome: Outcome,
+    deductions: Vec<Deduction>,
+    // Allergen to the ingredients it's in under at least one assignment.
+    possible: matching::Candidates<String, String>,
+}
+
+impl AllergenSolution {
+    fn possible_allergen_ingredients(&self) -> HashSet<&str> {
+        if let Outcome::Impossible(allergens) = &self.outcome {
+            panic!("Too few ingredients can contain all of: {:?}", allergens);
         }
+        self.possible.values().flatten().map(String::as_str).collect()
     }
 }
 
-fn determine_allergens(
-    input: HashMap<String, Vec<HashSet<String>>>,
-    mut current_allergens: HashMap<String, String>,
-) -> HashMap<String, String> {
-    if input.is_empty() {
-        return current_allergens;
-    } else {
-        for (allergen, ingredients) in input.iter().sorted_by_key(|(k, _)| <&std::string::String>::clone(k)) {
-            let iter = ingredients.iter();
-            let first_set = ingredients.iter().next().unwrap().clone();
-            let result_of_intersections = iter.fold(first_set, |acc, set| acc.intersection(set).cloned().collect());
-            if result_of_intersections.len() == 1 {
-                //This ingredient is this allergen, fo' sho'. We need to remove it from the other sets.
-                let allergen_ingredient = result_of_intersections.iter().next().unwrap();
-                current_allergens.insert(allergen_ingredient.clone(), allergen.clone());
-                let mut new_input = input.clone();
-                new_input.remove(allergen);
-                remove_allergen_ingredient_from_map(&mut new_input, &allergen_ingredient);
-                return determine_allergens(new_input, current_allergens);
-            }
+// For each allergen, the ingredients shared by every food listing it, and the indices of those foods.
+fn candidates_for_allergens(foods: &[Food]) -> BTreeMap<String, (BTreeSet<String>, Vec<usize>)> {
+    let mut candidates: BTreeMap<String, (BTreeSet<String>, Vec<usize>)> = BTreeMap::new();
+    for (i, food) in foods.iter().enumerate() {
+        for allergen in food.allergens.iter() {
+            let (ingredients, listed_in) = candidates
+                .entry(allergen.clone())
+                .or_insert_with(|| (food.ingredients.iter().cloned().collect(), vec![]));
+            ingredients.retain(|ingredient| food.ingredients.contains(ingredient));
+            listed_in.push(i);
         }
     }
-    panic!("Shouldn't get here.");
+    candidates
+}
+
+fn solve_allergens(foods: &[Food]) -> AllergenSolution {
+    let candidates = candidates_for_allergens(foods);
+    let matcher = Matcher::new(
+        candidates
+            .iter()
+            .map(|(allergen, (ingredients, _))| (allergen.clone(), ingredients.clone()))
+            .collect(),
+    );
+    let report = matcher.solve();
+    let deductions = report
+        .forced
+        .into_iter()
+        .map(|forced| Deduction {
+            foods: candidates[&forced.left].1.clone(),
+            allergen: forced.left,
+            ingredient: forced.right,
+            ruled_out: forced.ruled_out,
+        })
+        .collect();
+    AllergenSolution {
+        outcome: report.outcome,
+        deductions,
+        possible: matcher.possible_pairs(),
+    }
 }
 
 #[derive(Debug, Clone)]
 struct Input {
-    allergens_to_ingredients: HashMap<String, Vec<HashSet<String>>>,
+    foods: Vec<Food>,
     list_of_individual_ingredients: Vec<String>,
 }
 
@@ -128,4 +199,56 @@ mod tests {
         let input = parse_input(&fs::read_to_string("./test_inputs/day21").unwrap());
         assert_eq!(part_2(input), "mxmxvkd,sqjhc,fvjkl");
     }
+
+    #[test]
+    fn test_deductions_name_their_foods() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day21").unwrap());
+        let solution = solve_allergens(&input.foods);
+        let explained: Vec<String> = solution.deductions.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            explained,
+            vec![
+                "mxmxvkd contains dairy: foods 1, 2 share it",
+                "sqjhc contains fish: foods 1, 4 share it, and every other shared ingredient is taken \
+                 (mxmxvkd contains dairy)",
+                "fvjkl contains soy: foods 3 share it, and every other shared ingredient is taken \
+                 (sqjhc contains fish)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_allergens() {
+        let input = parse_input("aaa bbb ccc (contains dairy, fish)\naaa bbb ddd (contains fish)");
+        let solution = solve_allergens(&input.foods);
+        assert!(solution.deductions.is_empty());
+        match solution.outcome {
+            Outcome::Multiple(assignments) => assert_eq!(assignments.len(), 4),
+            outcome => panic!("Expected an ambiguous outcome, got {:?}", outcome),
+        }
+        assert_eq!(part_1(input), 1);
+    }
+
+    #[test]
+    fn test_contradictory_allergens() {
+        let input = parse_input("aaa bbb (contains dairy)\nccc (contains dairy)");
+        assert_eq!(
+            solve_allergens(&input.foods).outcome,
+            Outcome::Impossible(vec!["dairy".to_string()])
+        );
+
+        let input = parse_input("aaa (contains dairy)\naaa bbb (contains fish)\naaa ccc (contains soy, fish)");
+        let solution = solve_allergens(&input.foods);
+        assert_eq!(solution.deductions.len(), 2);
+        assert_eq!(
+            solution.outcome,
+            Outcome::Impossible(vec!["dairy".to_string(), "fish".to_string()])
+        );
+
+        let input = parse_input("aaa bbb (contains dairy, fish, soy)");
+        assert_eq!(
+            solve_allergens(&input.foods).outcome,
+            Outcome::Impossible(vec!["dairy".to_string(), "fish".to_string(), "soy".to_string()])
+        );
+    }
 }
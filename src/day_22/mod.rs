@@ -1,5 +1,10 @@
+use anyhow::anyhow;
+use hashbrown::{HashMap, HashSet};
+use itertools::Itertools;
 use std::collections::VecDeque;
-use std::fs;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::{fmt, fs};
 
 pub fn solve_part_1() -> Result<(), ()> {
     let input = parse_input(&fs::read_to_string("./inputs/day_22.txt").unwrap());
@@ -9,7 +14,7 @@ pub fn solve_part_1() -> Result<(), ()> {
 
 fn part_1(input: Game) -> u64 {
     let result = run_game(input);
-    score_player(result.winning_player())
+    score_result(&result)
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
@@ -18,13 +23,53 @@ pub fn solve_part_2() -> Result<(), ()> {
     Ok(())
 }
 
+pub fn solve_part_2_with_log() -> Result<(), ()> {
+    let input = parse_input(&fs::read_to_string("./inputs/day_22.txt").unwrap());
+    print_logged_game(input, &Rules::RECURSIVE_COMBAT);
+    Ok(())
+}
+
+// Plays the input, with any number of players, under the given part's rules changed by settings
+// like "recurse=0,ties=discard".
+pub fn solve_with_rules(part: usize, rules: &str, log: bool) -> Result<(), ()> {
+    let base = match part {
+        1 => Rules::COMBAT,
+        2 => Rules::RECURSIVE_COMBAT,
+        _ => {
+            println!("There is no part {}", part);
+            return Err(());
+        }
+    };
+    let rules = base.with_settings(rules).map_err(|e| println!("{}", e))?;
+    let input = parse_input(&fs::read_to_string("./inputs/day_22.txt").unwrap());
+    println!("Playing with {}", rules);
+    if log {
+        print_logged_game(input, &rules);
+    } else {
+        let result = play_game(input, &rules, 0, &mut GameLog::default(), &mut Shortcuts::all());
+        println!("Player {} wins with {}", result.winner + 1, score_result(&result));
+    }
+    Ok(())
+}
+
+fn print_logged_game(input: Game, rules: &Rules) {
+    let (result, log) = run_game_logged(input, rules);
+    for event in log.events.iter() {
+        println!("{}", event);
+    }
+    let (winner, _) = replay(&log.events, 0).expect("The log doesn't replay");
+    assert_eq!(winner, result.winner, "The replayed winner differs from the game's");
+    println!("{}", log.stats);
+    println!("{}", score_result(&result));
+}
+
 fn part_2(input: Game) -> u64 {
     let result = run_recursive_game(input);
+    score_result(&result)
+}
 
-    match result {
-        GameResult::PlayerOneWin(game) => score_player(game.winning_player()),
-        GameResult::PlayerTwoWin(game) => score_player(game.winning_player()),
-    }
+fn score_result(result: &GameResult) -> u64 {
+    score_player(&result.game.players[result.winner])
 }
 
 fn parse_player_input(player: &str) -> VecDeque<u32> {
@@ -35,31 +80,41 @@ fn parse_input(input: &str) -> Game {
     let players: Vec<_> = input.split("\n\n").map(parse_player_input).collect();
     Game {
         players,
-        previous_states_this_game: vec![],
+        previous_states_this_game: HashSet::new(),
     }
 }
 
 #[derive(Debug)]
 struct Game {
     players: Vec<VecDeque<u32>>,
-    previous_states_this_game: Vec<Vec<VecDeque<u32>>>,
+    previous_states_this_game: HashSet<Fingerprint>,
+}
+
+// Every deck in one allocation: the length of each deck, then the cards of each in order.
+type Fingerprint = Vec<u32>;
+
+fn fingerprint(decks: &[VecDeque<u32>]) -> Fingerprint {
+    let mut fingerprint = Vec::with_capacity(decks.len() + decks.iter().map(VecDeque::len).sum::<usize>());
+    fingerprint.extend(decks.iter().map(|deck| deck.len() as u32));
+    fingerprint.extend(decks.iter().flatten());
+    fingerprint
 }
 
 impl Game {
-    fn winning_player(&self) -> &VecDeque<u32> {
-        self.players.iter().find(|player| !player.is_empty()).unwrap()
+    fn new(players: Vec<VecDeque<u32>>) -> Game {
+        Game {
+            players,
+            previous_states_this_game: HashSet::new(),
+        }
     }
 
-    fn check_for_previous_state(&self) -> bool {
-        let most_recently_added = self.previous_states_this_game.last();
-        match most_recently_added {
-            Some(most_recent_state) => self
-                .previous_states_this_game
-                .iter()
-                .take(self.previous_states_this_game.len() - 1)
-                .any(|state| *state == *most_recent_state),
-            None => false,
-        }
+    fn players_left(&self) -> usize {
+        self.players.iter().filter(|deck| !deck.is_empty()).count()
+    }
+
+    // Remembers these decks for the rest of the game, returning whether they were already seen.
+    fn check_for_previous_state(&mut self) -> bool {
+        !self.previous_states_this_game.insert(fingerprint(&self.players))
     }
 }
 
@@ -72,91 +127,520 @@ fn score_player(player: &VecDeque<u32>) -> u64 {
         .sum()
 }
 
-fn run_game(game: Game) -> Game {
-    let mut player_1 = game.players[0].clone();
-    let mut player_2 = game.players[1].clone();
+// How a round is settled when the cards are compared directly and more than one player has the
+// highest card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tie {
+    FirstPlayer,
+    LastPlayer,
+    // Nobody wins the round, and its cards leave the game.
+    Discard,
+}
+
+// The order a round's winner puts the played cards on the bottom of their deck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReturnOrder {
+    // Their own card, then everyone else's in player order.
+    WinnerFirst,
+    Descending,
+    PlayerOrder,
+}
 
-    while !player_1.is_empty() && !player_2.is_empty() {
-        let (top_1, top_2) = (player_1.pop_front().unwrap(), player_2.pop_front().unwrap());
-        if top_1 > top_2 {
-            player_1.push_back(top_1);
-            player_1.push_back(top_2);
-        } else {
-            player_2.push_back(top_2);
-            player_2.push_back(top_1);
-        };
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rules {
+    // A round is settled by a sub-game when every player in it has at least this many cards beyond
+    // the value of the card they played. Each then plays the sub-game with that many cards.
+    recursion_threshold: Option<usize>,
+    // Whether a game ends, won by the first player still in it, when the decks are in a state
+    // they've been in before. Without it, some decks play forever.
+    repeats_end_game: bool,
+    ties: Tie,
+    return_order: ReturnOrder,
+}
+
+impl Rules {
+    const COMBAT: Rules = Rules {
+        recursion_threshold: None,
+        repeats_end_game: false,
+        ties: Tie::LastPlayer,
+        return_order: ReturnOrder::WinnerFirst,
+    };
+
+    const RECURSIVE_COMBAT: Rules = Rules {
+        recursion_threshold: Some(0),
+        repeats_end_game: true,
+        ties: Tie::LastPlayer,
+        return_order: ReturnOrder::WinnerFirst,
+    };
+
+    // The decks for the sub-game settling this round, if it goes to one.
+    fn sub_game_decks(&self, decks: &[VecDeque<u32>], played: &[(usize, u32)]) -> Option<Vec<VecDeque<u32>>> {
+        let threshold = self.recursion_threshold?;
+        if !played
+            .iter()
+            .all(|&(player, card)| decks[player].len() >= card as usize + threshold)
+        {
+            return None;
+        }
+        let mut sub_decks = vec![VecDeque::new(); decks.len()];
+        for &(player, card) in played {
+            sub_decks[player] = decks[player].iter().copied().take(card as usize).collect();
+        }
+        Some(sub_decks)
     }
-    let players = vec![player_1, player_2];
-    Game {
-        players,
-        previous_states_this_game: vec![],
+
+    fn highest_card_winner(&self, played: &[(usize, u32)]) -> Option<usize> {
+        let highest = played.iter().map(|&(_, card)| card).max()?;
+        let mut holders = played
+            .iter()
+            .filter(|&&(_, card)| card == highest)
+            .map(|&(player, _)| player);
+        match self.ties {
+            Tie::FirstPlayer => holders.next(),
+            Tie::LastPlayer => holders.next_back(),
+            Tie::Discard => holders.exactly_one().ok(),
+        }
+    }
+
+    fn returned_cards(&self, winner: usize, played: &[(usize, u32)]) -> Vec<u32> {
+        match self.return_order {
+            ReturnOrder::WinnerFirst => played
+                .iter()
+                .filter(|&&(player, _)| player == winner)
+                .chain(played.iter().filter(|&&(player, _)| player != winner))
+                .map(|&(_, card)| card)
+                .collect(),
+            ReturnOrder::Descending => played.iter().map(|&(_, card)| card).sorted().rev().collect(),
+            ReturnOrder::PlayerOrder => played.iter().map(|&(_, card)| card).collect(),
+        }
+    }
+}
+
+impl Rules {
+    // These rules with comma separated settings changed, e.g. "recurse=never,ties=discard".
+    fn with_settings(self, text: &str) -> anyhow::Result<Rules> {
+        let mut rules = self;
+        for setting in text.split(',').map(str::trim).filter(|setting| !setting.is_empty()) {
+            let (key, value) = setting
+                .splitn(2, '=')
+                .collect_tuple()
+                .ok_or_else(|| anyhow!("Expected \"<rule>=<value>\", found: {}", setting))?;
+            match (key, value) {
+                ("recurse", "never") => rules.recursion_threshold = None,
+                ("recurse", threshold) => {
+                    rules.recursion_threshold = Some(
+                        threshold
+                            .parse()
+                            .map_err(|_| anyhow!("Invalid recursion threshold: {}", threshold))?,
+                    )
+                }
+                ("repeats", "end") => rules.repeats_end_game = true,
+                ("repeats", "continue") => rules.repeats_end_game = false,
+                ("ties", "first") => rules.ties = Tie::FirstPlayer,
+                ("ties", "last") => rules.ties = Tie::LastPlayer,
+                ("ties", "discard") => rules.ties = Tie::Discard,
+                ("return", "winner-first") => rules.return_order = ReturnOrder::WinnerFirst,
+                ("return", "descending") => rules.return_order = ReturnOrder::Descending,
+                ("return", "player-order") => rules.return_order = ReturnOrder::PlayerOrder,
+                _ => return Err(anyhow!("Unknown rule: {}", setting)),
+            }
+        }
+        Ok(rules)
     }
 }
 
+impl FromStr for Rules {
+    type Err = anyhow::Error;
+
+    // Comma separated settings, starting from the rules of recursive combat.
+    fn from_str(text: &str) -> anyhow::Result<Self> {
+        Rules::RECURSIVE_COMBAT.with_settings(text)
+    }
+}
+
+impl Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.recursion_threshold {
+            Some(threshold) => write!(f, "recurse={}", threshold)?,
+            None => write!(f, "recurse=never")?,
+        }
+        let repeats = if self.repeats_end_game { "end" } else { "continue" };
+        let ties = match self.ties {
+            Tie::FirstPlayer => "first",
+            Tie::LastPlayer => "last",
+            Tie::Discard => "discard",
+        };
+        let return_order = match self.return_order {
+            ReturnOrder::WinnerFirst => "winner-first",
+            ReturnOrder::Descending => "descending",
+            ReturnOrder::PlayerOrder => "player-order",
+        };
+        write!(f, ",repeats={},ties={},return={}", repeats, ties, return_order)
+    }
+}
+
+fn run_game(game: Game) -> GameResult {
+    play_game(
+        game,
+        &Rules::COMBAT,
+        0,
+        &mut GameLog::default(),
+        &mut Shortcuts::default(),
+    )
+}
+
 #[derive(Debug)]
-enum GameResult {
-    PlayerOneWin(Game),
-    PlayerTwoWin(Game),
-}
-
-fn run_recursive_game(mut game: Game) -> GameResult {
-    let mut player_1 = game.players[0].clone();
-    let mut player_2 = game.players[1].clone();
-    while !player_1.is_empty() && !player_2.is_empty() {
-        if game.check_for_previous_state() {
-            // println!("Exiting as we have seen this state before: {:#?}", vec![player_1.clone(), player_2.clone()]);
-            return GameResult::PlayerOneWin(game);
-        } else {
-            game.previous_states_this_game
-                .push(vec![player_1.clone(), player_2.clone()])
-        }
-        let (top_1, top_2) = (player_1.pop_front().unwrap(), player_2.pop_front().unwrap());
-        // println!("Player 1 plays: {}, player_2 plays: {}", top_1, top_2);
-        if player_1.len() >= (top_1 as usize) && player_2.len() >= (top_2 as usize) {
-            // println!("Recursing");
-            let new_player_1 = player_1.iter().cloned().take(top_1 as usize).collect();
-            let new_player_2 = player_2.iter().cloned().take(top_2 as usize).collect();
-            let this_round_winner = run_recursive_game(Game {
-                players: vec![new_player_1, new_player_2],
-                previous_states_this_game: vec![],
-            });
-            match this_round_winner {
-                GameResult::PlayerOneWin(_) => {
-                    // println!("Player one won");
-                    player_1.push_back(top_1);
-                    player_1.push_back(top_2);
+struct GameResult {
+    winner: usize,
+    game: Game,
+}
+
+// Games are numbered in the order they start, from 0 for the outermost one. Players are indices
+// into the game's decks, which keep their places once empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Event {
+    GameStarted {
+        game: usize,
+        depth: usize,
+        decks: Vec<Vec<u32>>,
+    },
+    Round {
+        game: usize,
+        round: usize,
+        // The players still in the game, with the card each played.
+        played: Vec<(usize, u32)>,
+        // None when a tie discards the cards.
+        winner: Option<usize>,
+        sub_game: Option<usize>,
+        // The cards put on the bottom of the winner's deck, in order.
+        returned: Vec<u32>,
+    },
+    RepeatedState {
+        game: usize,
+        round: usize,
+    },
+    GameWon {
+        game: usize,
+        winner: usize,
+    },
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::GameStarted { game, depth, decks } => write!(
+                f,
+                "game {} starts at depth {} with decks {}",
+                game,
+                depth,
+                decks.iter().map(|deck| format!("{:?}", deck)).join(" ")
+            ),
+            Event::Round {
+                game,
+                round,
+                played,
+                winner,
+                sub_game,
+                ..
+            } => {
+                write!(
+                    f,
+                    "game {} round {}: played {}, ",
+                    game,
+                    round,
+                    played
+                        .iter()
+                        .map(|(player, card)| format!("{} by player {}", card, player + 1))
+                        .join(", ")
+                )?;
+                match winner {
+                    Some(winner) => write!(f, "player {} wins", winner + 1)?,
+                    None => write!(f, "the tie is discarded")?,
                 }
-                GameResult::PlayerTwoWin(_) => {
-                    // println!("Player two won");
-                    player_2.push_back(top_2);
-                    player_2.push_back(top_1);
+                match sub_game {
+                    Some(sub_game) => write!(f, " by winning game {}", sub_game),
+                    None => Ok(()),
                 }
             }
+            Event::RepeatedState { game, round } => {
+                write!(f, "game {} round {}: the decks repeat", game, round)
+            }
+            Event::GameWon { game, winner } => write!(f, "game {} is won by player {}", game, winner + 1),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Stats {
+    games: usize,
+    rounds: usize,
+    max_depth: usize,
+    repeated_states: usize,
+    cached_sub_games: usize,
+    highest_card_wins: usize,
+}
+
+impl Stats {
+    fn sub_games(&self) -> usize {
+        self.games.saturating_sub(1)
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rounds over {} sub-games, recursing at most {} deep, with {} ended by repeated decks; \
+             {} sub-games were cached and {} won by the first player holding the highest card",
+            self.rounds,
+            self.sub_games(),
+            self.max_depth,
+            self.repeated_states,
+            self.cached_sub_games,
+            self.highest_card_wins
+        )
+    }
+}
+
+// Stats are always counted; the events themselves are only kept when recording, as a full log of
+// the recursive game runs to millions of rounds.
+#[derive(Debug, Default)]
+struct GameLog {
+    recording: bool,
+    events: Vec<Event>,
+    stats: Stats,
+}
+
+impl GameLog {
+    fn recording() -> GameLog {
+        GameLog {
+            recording: true,
+            ..GameLog::default()
+        }
+    }
+
+    fn record<F: FnOnce() -> Event>(&mut self, event: F) {
+        if self.recording {
+            self.events.push(event());
+        }
+    }
+
+    fn start_game(&mut self, depth: usize, decks: &[VecDeque<u32>]) -> usize {
+        let game = self.stats.games;
+        self.stats.games += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        self.record(|| Event::GameStarted {
+            game,
+            depth,
+            decks: decks.iter().map(|deck| deck.iter().copied().collect()).collect(),
+        });
+        game
+    }
+}
+
+// Ways of settling a sub-game without playing it, neither of which changes who wins. A sub-game's
+// winner only depends on its starting decks. And when the first player in it holds a highest card
+// that's at least the number of cards in play, nobody can hold enough cards to recurse with it, so
+// it can never be lost, and they either outlast everyone or win on a repeated state.
+#[derive(Debug, Default)]
+struct Shortcuts {
+    memoize_sub_games: bool,
+    highest_card_wins: bool,
+    sub_game_winners: HashMap<Fingerprint, usize>,
+}
+
+impl Shortcuts {
+    fn all() -> Shortcuts {
+        Shortcuts {
+            memoize_sub_games: true,
+            highest_card_wins: true,
+            ..Shortcuts::default()
+        }
+    }
+
+    fn highest_card_winner(&self, rules: &Rules, decks: &[VecDeque<u32>]) -> Option<usize> {
+        if !self.highest_card_wins || !rules.repeats_end_game {
+            return None;
+        }
+        let first = decks.iter().position(|deck| !deck.is_empty())?;
+        let highest = *decks[first].iter().max()?;
+        let in_play = decks.iter().map(VecDeque::len).sum::<usize>();
+        let unbeaten = decks[first + 1..].iter().flatten().all(|&card| card < highest);
+        if highest as usize >= in_play && unbeaten {
+            Some(first)
         } else {
-            // println!("Playing plain");
-            if top_1 > top_2 {
-                // println!("Player one won");
-                player_1.push_back(top_1);
-                player_1.push_back(top_2);
-            } else {
-                // println!("Player two won");
-                player_2.push_back(top_2);
-                player_2.push_back(top_1);
-            };
+            None
         }
     }
+}
 
-    let players = vec![player_1.clone(), player_2];
-    let game_to_return = Game {
-        players,
-        previous_states_this_game: vec![],
+fn run_recursive_game(game: Game) -> GameResult {
+    play_game(
+        game,
+        &Rules::RECURSIVE_COMBAT,
+        0,
+        &mut GameLog::default(),
+        &mut Shortcuts::all(),
+    )
+}
+
+// Logs the game with the same shortcuts as the solver, so the stats count their hits. Each sub-game
+// that's played is logged in full and can be replayed; a round settled by a shortcut names no sub-game.
+fn run_game_logged(game: Game, rules: &Rules) -> (GameResult, GameLog) {
+    let mut log = GameLog::recording();
+    let result = play_game(game, rules, 0, &mut log, &mut Shortcuts::all());
+    (result, log)
+}
+
+fn play_game(mut game: Game, rules: &Rules, depth: usize, log: &mut GameLog, shortcuts: &mut Shortcuts) -> GameResult {
+    let game_id = log.start_game(depth, &game.players);
+    let mut round = 0;
+    // Should every player run out at once, the game goes to the first of those in the last round.
+    let mut last_round_winner = 0;
+    while game.players_left() > 1 {
+        round += 1;
+        if rules.repeats_end_game && game.check_for_previous_state() {
+            let winner = game.players.iter().position(|deck| !deck.is_empty()).unwrap();
+            log.stats.repeated_states += 1;
+            log.record(|| Event::RepeatedState { game: game_id, round });
+            log.record(|| Event::GameWon { game: game_id, winner });
+            return GameResult { winner, game };
+        }
+        log.stats.rounds += 1;
+        let played: Vec<(usize, u32)> = game
+            .players
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(player, deck)| deck.pop_front().map(|card| (player, card)))
+            .collect();
+        last_round_winner = played[0].0;
+        let (winner, sub_game) = match rules.sub_game_decks(&game.players, &played) {
+            Some(decks) => {
+                if let Some(winner) = shortcuts.highest_card_winner(rules, &decks) {
+                    log.stats.highest_card_wins += 1;
+                    (Some(winner), None)
+                } else {
+                    let starting_decks = fingerprint(&decks);
+                    if let Some(&winner) = shortcuts.sub_game_winners.get(&starting_decks) {
+                        log.stats.cached_sub_games += 1;
+                        (Some(winner), None)
+                    } else {
+                        let sub_game = log.stats.games;
+                        let this_round_winner = play_game(Game::new(decks), rules, depth + 1, log, shortcuts).winner;
+                        if shortcuts.memoize_sub_games {
+                            shortcuts.sub_game_winners.insert(starting_decks, this_round_winner);
+                        }
+                        (Some(this_round_winner), Some(sub_game))
+                    }
+                }
+            }
+            None => (rules.highest_card_winner(&played), None),
+        };
+        let returned = match winner {
+            Some(winner) => {
+                let returned = rules.returned_cards(winner, &played);
+                game.players[winner].extend(returned.iter().copied());
+                last_round_winner = winner;
+                returned
+            }
+            None => vec![],
+        };
+        log.record(|| Event::Round {
+            game: game_id,
+            round,
+            played,
+            winner,
+            sub_game,
+            returned,
+        });
+    }
+
+    let winner = game
+        .players
+        .iter()
+        .position(|deck| !deck.is_empty())
+        .unwrap_or(last_round_winner);
+    log.record(|| Event::GameWon { game: game_id, winner });
+    GameResult { winner, game }
+}
+
+// Replays a game from its logged starting decks, checking every round against the decks and every
+// sub-game's winner against its own replay. Returns the winner and the final decks.
+fn replay(events: &[Event], game: usize) -> anyhow::Result<(usize, Vec<VecDeque<u32>>)> {
+    let mut events_by_game: HashMap<usize, Vec<&Event>> = HashMap::new();
+    for event in events {
+        let game = match event {
+            Event::GameStarted { game, .. }
+            | Event::Round { game, .. }
+            | Event::RepeatedState { game, .. }
+            | Event::GameWon { game, .. } => *game,
+        };
+        events_by_game.entry(game).or_insert_with(Vec::new).push(event);
+    }
+    replay_game(&events_by_game, game)
+}
+
+fn replay_game(
+    events_by_game: &HashMap<usize, Vec<&Event>>,
+    game: usize,
+) -> anyhow::Result<(usize, Vec<VecDeque<u32>>)> {
+    let events = events_by_game
+        .get(&game)
+        .ok_or_else(|| anyhow!("Game {} isn't in the log", game))?;
+    let mut decks: Vec<VecDeque<u32>> = match events.first() {
+        Some(Event::GameStarted { decks, .. }) => decks.iter().map(|deck| deck.iter().copied().collect()).collect(),
+        _ => return Err(anyhow!("Game {} doesn't start with its decks", game)),
     };
-    // println!("Reached the end of this game where player {} won", if !player_1.is_empty() { "1" } else { "2" });
-    if !player_1.is_empty() {
-        GameResult::PlayerOneWin(game_to_return)
-    } else {
-        GameResult::PlayerTwoWin(game_to_return)
+    for event in events.iter().skip(1) {
+        match event {
+            Event::Round {
+                round,
+                played,
+                winner,
+                sub_game,
+                returned,
+                ..
+            } => {
+                for &(player, card) in played.iter() {
+                    if decks.get_mut(player).and_then(VecDeque::pop_front) != Some(card) {
+                        return Err(anyhow!("Game {} round {} plays a card that isn't on top", game, round));
+                    }
+                }
+                if let Some(sub_game) = sub_game {
+                    let (sub_winner, _) = replay_game(events_by_game, *sub_game)?;
+                    if Some(sub_winner) != *winner {
+                        return Err(anyhow!(
+                            "Game {} round {} disagrees with game {}",
+                            game,
+                            round,
+                            sub_game
+                        ));
+                    }
+                }
+                let expected_return = match winner {
+                    Some(_) => played.iter().map(|&(_, card)| card).sorted().collect(),
+                    None => vec![],
+                };
+                if returned.iter().copied().sorted().collect::<Vec<_>>() != expected_return {
+                    return Err(anyhow!(
+                        "Game {} round {} returns other cards than were played",
+                        game,
+                        round
+                    ));
+                }
+                if let Some(winner) = winner {
+                    decks
+                        .get_mut(*winner)
+                        .ok_or_else(|| anyhow!("Game {} round {} has no player {}", game, round, winner))?
+                        .extend(returned.iter().copied());
+                }
+            }
+            Event::RepeatedState { .. } => {}
+            Event::GameWon { winner, .. } => return Ok((*winner, decks)),
+            Event::GameStarted { .. } => return Err(anyhow!("Game {} starts twice", game)),
+        }
     }
+    Err(anyhow!("Game {} never finishes", game))
 }
 
 #[cfg(test)]
@@ -174,4 +658,336 @@ mod tests {
         let input = parse_input(&fs::read_to_string("./test_inputs/day22").unwrap());
         assert_eq!(part_2(input), 291);
     }
+
+    #[test]
+    fn test_game_log_and_stats() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day22").unwrap());
+        let (result, log) = run_game_logged(input, &Rules::RECURSIVE_COMBAT);
+        assert_eq!(score_result(&result), 291);
+        assert_eq!(
+            log.stats,
+            Stats {
+                games: 4,
+                rounds: 28,
+                max_depth: 2,
+                repeated_states: 0,
+                cached_sub_games: 1,
+                highest_card_wins: 0,
+            }
+        );
+        assert_eq!(log.stats.sub_games(), 3);
+        assert_eq!(
+            log.events.iter().filter(|e| matches!(e, Event::Round { .. })).count(),
+            log.stats.rounds
+        );
+        assert_eq!(
+            log.events[0],
+            Event::GameStarted {
+                game: 0,
+                depth: 0,
+                decks: vec![vec![9, 2, 6, 3, 1], vec![5, 8, 4, 7, 10]],
+            }
+        );
+        assert!(log.events.contains(&Event::Round {
+            game: 0,
+            round: 9,
+            played: vec![(0, 4), (1, 3)],
+            winner: Some(1),
+            sub_game: Some(1),
+            returned: vec![3, 4],
+        }));
+        assert_eq!(log.events.last().unwrap().to_string(), "game 0 is won by player 2");
+    }
+
+    #[test]
+    fn test_replaying_the_log() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day22").unwrap());
+        let (result, log) = run_game_logged(input, &Rules::RECURSIVE_COMBAT);
+        let (winner, decks) = replay(&log.events, 0).unwrap();
+        assert_eq!(winner, result.winner);
+        assert_eq!(decks[1], vec![7, 5, 6, 2, 4, 1, 10, 8, 9, 3]);
+
+        let mut tampered = log.events.clone();
+        let first_round = tampered
+            .iter()
+            .position(|e| matches!(e, Event::Round { game: 0, .. }))
+            .unwrap();
+        if let Event::Round { played, .. } = &mut tampered[first_round] {
+            let (first, second) = (played[0].1, played[1].1);
+            played[0].1 = second;
+            played[1].1 = first;
+        }
+        assert!(replay(&tampered, 0).is_err());
+        assert!(replay(&log.events[..log.events.len() - 1], 0).is_err());
+    }
+
+    #[test]
+    fn test_repeated_state_is_logged() {
+        let input = parse_input("Player 1:\n43\n19\n\nPlayer 2:\n2\n29\n14");
+        let (result, log) = run_game_logged(input, &Rules::RECURSIVE_COMBAT);
+        assert_eq!(result.winner, 0);
+        assert_eq!(log.stats.repeated_states, 1);
+        assert!(log
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::RepeatedState { game: 0, .. })));
+        assert_eq!(replay(&log.events, 0).unwrap().0, 0);
+    }
+
+    // The recursive game as it was before hashing states and settling sub-games early, kept to
+    // check and benchmark the faster one against.
+    fn play_reference_game(mut player_1: VecDeque<u32>, mut player_2: VecDeque<u32>) -> (usize, u64) {
+        let mut previous_states: Vec<Vec<VecDeque<u32>>> = vec![];
+        while !player_1.is_empty() && !player_2.is_empty() {
+            let state = vec![player_1.clone(), player_2.clone()];
+            if previous_states.contains(&state) {
+                return (0, score_player(&player_1));
+            }
+            previous_states.push(state);
+            let (top_1, top_2) = (player_1.pop_front().unwrap(), player_2.pop_front().unwrap());
+            let winner = if player_1.len() >= (top_1 as usize) && player_2.len() >= (top_2 as usize) {
+                play_reference_game(
+                    player_1.iter().cloned().take(top_1 as usize).collect(),
+                    player_2.iter().cloned().take(top_2 as usize).collect(),
+                )
+                .0
+            } else if top_1 > top_2 {
+                0
+            } else {
+                1
+            };
+            if winner == 0 {
+                player_1.push_back(top_1);
+                player_1.push_back(top_2);
+            } else {
+                player_2.push_back(top_2);
+                player_2.push_back(top_1);
+            }
+        }
+        if player_1.is_empty() {
+            (1, score_player(&player_2))
+        } else {
+            (0, score_player(&player_1))
+        }
+    }
+
+    // Deals the cards 1 to `cards` shuffled by a fixed linear congruential generator.
+    fn deal(cards: u32, seed: u64) -> Game {
+        let mut deck: Vec<u32> = (1..=cards).collect();
+        let mut state = seed;
+        for i in (1..deck.len()).rev() {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            deck.swap(i, (state >> 33) as usize % (i + 1));
+        }
+        let half = deck.len() / 2;
+        Game {
+            players: vec![
+                deck[..half].iter().copied().collect(),
+                deck[half..].iter().copied().collect(),
+            ],
+            previous_states_this_game: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_shortcuts_match_reference_game() {
+        for seed in 0..30 {
+            let game = deal(20, seed);
+            let expected = play_reference_game(game.players[0].clone(), game.players[1].clone());
+            let result = run_recursive_game(game);
+            assert_eq!((result.winner, score_result(&result)), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_shortcuts_are_counted() {
+        let input = parse_input(&fs::read_to_string("./test_inputs/day22").unwrap());
+        let mut log = GameLog::default();
+        let result = play_game(input, &Rules::RECURSIVE_COMBAT, 0, &mut log, &mut Shortcuts::all());
+        assert_eq!(score_result(&result), 291);
+        // Player 2 holds the highest card in every sub-game, but one is dealt the same decks twice.
+        assert_eq!(log.stats.highest_card_wins, 0);
+        assert_eq!(log.stats.cached_sub_games, 1);
+        assert_eq!(log.stats.sub_games(), 3);
+
+        let game = deal(30, 7);
+        let mut full = GameLog::default();
+        let full_result = play_game(
+            deal(30, 7),
+            &Rules::RECURSIVE_COMBAT,
+            0,
+            &mut full,
+            &mut Shortcuts::default(),
+        );
+        let mut short = GameLog::default();
+        let short_result = play_game(game, &Rules::RECURSIVE_COMBAT, 0, &mut short, &mut Shortcuts::all());
+        assert_eq!(score_result(&full_result), score_result(&short_result));
+        assert!(short.stats.rounds < full.stats.rounds);
+        assert!(short.stats.highest_card_wins + short.stats.cached_sub_games > 0);
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_recursive_game() {
+        use std::time::Instant;
+        // Some deals take the reference game minutes, so these are ones it finishes in a few seconds.
+        for &seed in [0, 1, 4, 5, 6, 8].iter() {
+            let game = deal(50, seed);
+            let start = Instant::now();
+            let expected = play_reference_game(game.players[0].clone(), game.players[1].clone());
+            let reference = start.elapsed();
+            let start = Instant::now();
+            let result = run_recursive_game(game);
+            let fast = start.elapsed();
+            assert_eq!((result.winner, score_result(&result)), expected);
+            println!("seed {}: reference {:?}, with shortcuts {:?}", seed, reference, fast);
+        }
+    }
+
+    #[test]
+    fn test_rules_text() {
+        assert_eq!(
+            Rules::RECURSIVE_COMBAT.to_string(),
+            "recurse=0,repeats=end,ties=last,return=winner-first"
+        );
+        assert_eq!("".parse::<Rules>().unwrap(), Rules::RECURSIVE_COMBAT);
+        assert_eq!(
+            "recurse=never, repeats=continue".parse::<Rules>().unwrap(),
+            Rules::COMBAT
+        );
+        let rules: Rules = "recurse=2,ties=discard,return=descending".parse().unwrap();
+        assert_eq!(rules.to_string().parse::<Rules>().unwrap(), rules);
+        assert!("recurse=-1".parse::<Rules>().is_err());
+        assert!("ties=split".parse::<Rules>().is_err());
+        assert!("recurse".parse::<Rules>().is_err());
+        assert_eq!(Rules::COMBAT.with_settings("").unwrap(), Rules::COMBAT);
+        assert_eq!(
+            Rules::COMBAT.with_settings("ties=discard").unwrap(),
+            Rules {
+                ties: Tie::Discard,
+                ..Rules::COMBAT
+            }
+        );
+    }
+
+    #[test]
+    fn test_three_player_combat() {
+        let input = parse_input("Player 1:\n5\n1\n\nPlayer 2:\n3\n6\n\nPlayer 3:\n4\n2");
+        let (result, log) = run_game_logged(input, &Rules::COMBAT);
+        // 5 takes the first round and 6 the second, leaving player 3 out and player 2 holding the 6.
+        assert_eq!(result.winner, 1);
+        assert_eq!(
+            log.events[1],
+            Event::Round {
+                game: 0,
+                round: 1,
+                played: vec![(0, 5), (1, 3), (2, 4)],
+                winner: Some(0),
+                sub_game: None,
+                returned: vec![5, 3, 4],
+            }
+        );
+        assert_eq!(
+            log.events[2].to_string(),
+            "game 0 round 2: played 1 by player 1, 6 by player 2, 2 by player 3, player 2 wins"
+        );
+        assert_eq!(result.game.players[2], VecDeque::new());
+        assert_eq!(replay(&log.events, 0).unwrap().0, 1);
+        assert_eq!(result.game.players[1], vec![5, 1, 6, 4, 3, 2]);
+    }
+
+    #[test]
+    fn test_recursive_sub_games_keep_players_in_place() {
+        // Player 1 is out by the sub-game in round 2, which players 2 and 3 play from their seats.
+        let input = parse_input("Player 1:\n1\n\nPlayer 2:\n9\n1\n7\n\nPlayer 3:\n8\n1\n5");
+        // Without shortcuts, as player 2's 7 would settle the sub-game unplayed.
+        let mut log = GameLog::recording();
+        let result = play_game(input, &Rules::RECURSIVE_COMBAT, 0, &mut log, &mut Shortcuts::default());
+        let sub_game = log
+            .events
+            .iter()
+            .find(|event| matches!(event, Event::GameStarted { game: 1, .. }))
+            .unwrap();
+        assert_eq!(
+            *sub_game,
+            Event::GameStarted {
+                game: 1,
+                depth: 1,
+                decks: vec![vec![], vec![7], vec![5]],
+            }
+        );
+        assert_eq!(replay(&log.events, 0).unwrap().0, result.winner);
+    }
+
+    #[test]
+    fn test_tie_rules() {
+        let played = vec![(0, 4), (1, 7), (2, 7)];
+        let rules = |ties| Rules { ties, ..Rules::COMBAT };
+        assert_eq!(rules(Tie::FirstPlayer).highest_card_winner(&played), Some(1));
+        assert_eq!(rules(Tie::LastPlayer).highest_card_winner(&played), Some(2));
+        assert_eq!(rules(Tie::Discard).highest_card_winner(&played), None);
+
+        let input = parse_input("Player 1:\n3\n2\n\nPlayer 2:\n3\n1");
+        let (result, log) = run_game_logged(input, &rules(Tie::Discard));
+        assert_eq!(result.winner, 0);
+        assert_eq!(result.game.players, vec![VecDeque::from(vec![2, 1]), VecDeque::new()]);
+        assert_eq!(
+            log.events[1].to_string(),
+            "game 0 round 1: played 3 by player 1, 3 by player 2, the tie is discarded"
+        );
+        assert_eq!(replay(&log.events, 0).unwrap().1, result.game.players);
+
+        // Everyone running out in the same discarded tie leaves the game to the first of them.
+        let input = parse_input("Player 1:\n\nPlayer 2:\n3\n\nPlayer 3:\n3");
+        let (result, _) = run_game_logged(input, &rules(Tie::Discard));
+        assert_eq!(result.winner, 1);
+    }
+
+    #[test]
+    fn test_return_orders() {
+        let played = vec![(0, 4), (1, 9), (2, 6)];
+        let rules = |return_order| Rules {
+            return_order,
+            ..Rules::COMBAT
+        };
+        assert_eq!(
+            rules(ReturnOrder::WinnerFirst).returned_cards(2, &played),
+            vec![6, 4, 9]
+        );
+        assert_eq!(rules(ReturnOrder::Descending).returned_cards(2, &played), vec![9, 6, 4]);
+        assert_eq!(
+            rules(ReturnOrder::PlayerOrder).returned_cards(2, &played),
+            vec![4, 9, 6]
+        );
+    }
+
+    #[test]
+    fn test_variants_match_without_shortcuts() {
+        let variants = [
+            "recurse=0,ties=first,return=descending",
+            "recurse=1,return=player-order",
+            "recurse=0,ties=discard",
+        ];
+        for rules in variants.iter() {
+            let rules: Rules = rules.parse().unwrap();
+            for seed in 0..10 {
+                let mut decks = deal(18, seed).players;
+                let third = decks[1].split_off(4);
+                decks.push(third);
+                let (logged, log) = run_game_logged(Game::new(decks.clone()), &rules);
+                let fast = play_game(
+                    Game::new(decks),
+                    &rules,
+                    0,
+                    &mut GameLog::default(),
+                    &mut Shortcuts::all(),
+                );
+                assert_eq!(fast.winner, logged.winner, "{} seed {}", rules, seed);
+                assert_eq!(fast.game.players, logged.game.players, "{} seed {}", rules, seed);
+                assert_eq!(replay(&log.events, 0).unwrap().0, logged.winner);
+            }
+        }
+    }
 }
@@ -1,22 +1,26 @@
 use std::cell::RefCell;
-use std::fmt::Display;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
 
+// A circular doubly linked list. Nodes are handed out as `NodeRef`s, which stay valid as they move
+// around or out of the list, so callers can keep their own index of nodes.
 pub struct List<T> {
     head: Link<T>,
-    tail: Link<T>,
+    len: usize,
 }
 
-pub type Link<T> = Option<Rc<RefCell<Node<T>>>>;
+pub type NodeRef<T> = Rc<RefCell<Node<T>>>;
+pub type Link<T> = Option<NodeRef<T>>;
 
+// Links forward are strong and back are weak, so the only cycle is from the tail to the head,
+// which `Drop` breaks.
 pub struct Node<T> {
     pub elem: T,
-    pub next: Link<T>,
-    pub prev: Link<T>,
+    next: Link<T>,
+    prev: Option<Weak<RefCell<Node<T>>>>,
 }
 
 impl<T> Node<T> {
-    fn new(elem: T) -> Rc<RefCell<Self>> {
+    fn new(elem: T) -> NodeRef<T> {
         Rc::new(RefCell::new(Node {
             elem,
             prev: None,
@@ -24,136 +28,249 @@ impl<T> Node<T> {
         }))
     }
 
-    pub fn set_next(&mut self, to_set: &Rc<RefCell<Node<T>>>) {
-        if self.next.is_some() {
-            panic!("Oh dear");
-        } else {
-            self.next = Some(Rc::clone(&to_set));
-        }
+    pub fn next(&self) -> Link<T> {
+        self.next.clone()
     }
+}
 
-    /// Returns the start of the removed list.
-    pub fn take_three_out(to_remove_after: Rc<RefCell<Node<T>>>) -> Rc<RefCell<Node<T>>> {
-        let removed_start: Rc<RefCell<Node<T>>> = Rc::clone(to_remove_after.borrow().next.as_ref().unwrap());
-        let removed_end = Rc::clone(
-            removed_start
-                .borrow()
-                .next
-                .as_ref()
-                .unwrap()
-                .borrow()
-                .next
-                .as_ref()
-                .unwrap(),
-        );
-        {
-            let mut changing = to_remove_after.borrow_mut();
-            changing.next = Some(Rc::clone(removed_end.borrow().next.as_ref().unwrap()))
+// A run of nodes taken out of a list, linked to each other but not in a circle.
+pub struct Segment<T> {
+    // Both None once the nodes are spliced back into a list.
+    first: Link<T>,
+    last: Link<T>,
+    len: usize,
+}
+
+impl<T> Segment<T> {
+    pub fn iter(&self) -> Iter<T> {
+        Iter {
+            next: self.first.clone(),
+            remaining: self.len,
         }
-        removed_start
-    }
-
-    pub fn insert_after(&mut self, to_insert: Rc<RefCell<Node<T>>>) {
-        let for_after_inserted = self.next.take().unwrap();
-        self.next = Some(Rc::clone(&to_insert));
-        let newly_inserteds_tail = Rc::clone(
-            to_insert
-                .borrow()
-                .next
-                .as_ref()
-                .unwrap()
-                .borrow()
-                .next
-                .as_ref()
-                .unwrap(),
-        );
-        let mut newly_inserteds_tail = newly_inserteds_tail.borrow_mut();
-        newly_inserteds_tail.next = Some(for_after_inserted);
     }
 }
 
+impl<T> Drop for Segment<T> {
+    fn drop(&mut self) {
+        unlink_all(self.first.take());
+    }
+}
+
+// Drops a chain of nodes one at a time, rather than recursing through every `next`.
+fn unlink_all<T>(mut current: Link<T>) {
+    while let Some(node) = current {
+        current = node.borrow_mut().next.take();
+    }
+}
+
+fn link<T>(from: &NodeRef<T>, to: &NodeRef<T>) {
+    from.borrow_mut().next = Some(Rc::clone(to));
+    to.borrow_mut().prev = Some(Rc::downgrade(from));
+}
+
+fn next_of<T>(node: &NodeRef<T>) -> NodeRef<T> {
+    node.borrow().next().expect("A node in a list always has a next node")
+}
+
+fn prev_of<T>(node: &NodeRef<T>) -> NodeRef<T> {
+    let prev = node.borrow().prev.as_ref().and_then(Weak::upgrade);
+    prev.expect("A node in a list always has a previous node")
+}
+
 impl<T> List<T> {
     pub fn new() -> Self {
-        List { head: None, tail: None }
+        List { head: None, len: 0 }
+    }
+
+    fn tail(&self) -> Link<T> {
+        self.head.as_ref().map(prev_of)
     }
 
-    pub fn push_back(&mut self, elem: T) -> Rc<RefCell<Node<T>>> {
+    pub fn push_back(&mut self, elem: T) -> NodeRef<T> {
         let new_tail = Node::new(elem);
-        let to_return = Rc::clone(&new_tail);
-        match self.tail.take() {
-            Some(old_tail) => {
-                old_tail.borrow_mut().next = Some(new_tail.clone());
-                new_tail.borrow_mut().prev = Some(old_tail);
-                self.tail = Some(new_tail);
-            }
+        match self.tail() {
+            Some(old_tail) => self.link_after(&old_tail, &new_tail, &new_tail),
             None => {
-                self.head = Some(new_tail.clone());
-                self.tail = Some(new_tail);
+                link(&new_tail, &new_tail);
+                self.head = Some(Rc::clone(&new_tail));
             }
         }
-        to_return
+        self.len += 1;
+        new_tail
+    }
+
+    // Takes out the `count` nodes following `node`, which has to stay behind.
+    pub fn remove_after(&mut self, node: &NodeRef<T>, count: usize) -> Option<Segment<T>> {
+        if count == 0 || count >= self.len {
+            return None;
+        }
+        let first = next_of(node);
+        let mut last = Rc::clone(&first);
+        for _ in 1..count {
+            last = next_of(&last);
+        }
+        let after = next_of(&last);
+        let head_removed = self
+            .iter_from(&first)
+            .take(count)
+            .any(|removed| matches!(self.head, Some(ref head) if Rc::ptr_eq(head, &removed)));
+        link(node, &after);
+        if head_removed {
+            self.head = Some(after);
+        }
+        first.borrow_mut().prev = None;
+        last.borrow_mut().next = None;
+        self.len -= count;
+        Some(Segment {
+            first: Some(first),
+            last: Some(last),
+            len: count,
+        })
+    }
+
+    // Puts a segment back in, straight after `node`.
+    pub fn splice_after(&mut self, node: &NodeRef<T>, mut segment: Segment<T>) {
+        // Taken out of the segment so that dropping it leaves them linked.
+        if let (Some(first), Some(last)) = (segment.first.take(), segment.last.take()) {
+            self.link_after(node, &first, &last);
+            self.len += segment.len;
+        }
+    }
+
+    // Links the chain from `first` to `last` in after `node`.
+    fn link_after(&mut self, node: &NodeRef<T>, first: &NodeRef<T>, last: &NodeRef<T>) {
+        let after = next_of(node);
+        link(last, &after);
+        link(node, first);
+    }
+
+    // Every node once, from `node` round to the one before it.
+    pub fn iter_from(&self, node: &NodeRef<T>) -> Iter<T> {
+        Iter {
+            next: Some(Rc::clone(node)),
+            remaining: self.len,
+        }
     }
 }
 
-pub fn print_list_items<T: Display>(node: Rc<RefCell<Node<T>>>, items: usize) {
-    let mut current_node = node;
-    for _ in 0..items {
-        println!("{}", current_node.borrow().elem);
-        let next_node = {
-            let borrowed_current = current_node.borrow();
-            Rc::clone(borrowed_current.next.as_ref().unwrap())
-        };
-        current_node = next_node;
+impl<T> Drop for List<T> {
+    fn drop(&mut self) {
+        unlink_all(self.head.take());
+    }
+}
+
+pub struct Iter<T> {
+    next: Link<T>,
+    remaining: usize,
+}
+
+impl<T> Iterator for Iter<T> {
+    type Item = NodeRef<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let current = self.next.take()?;
+        self.next = current.borrow().next();
+        Some(current)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::List;
-    use crate::day_23::linked_list::{print_list_items, Node};
-    use crate::day_23::LabelNodeMap;
+    use super::{List, NodeRef};
     use hashbrown::HashMap;
-    use std::cell::RefCell;
     use std::rc::Rc;
 
+    type LabelNodeMap = HashMap<u32, NodeRef<u32>>;
+
     fn setup_map() -> (List<u32>, LabelNodeMap) {
         let mut list: List<u32> = List::new();
-        let mut map: HashMap<u32, Rc<RefCell<Node<u32>>>> = HashMap::new();
-        let head = list.push_back(1);
-        map.insert(1, Rc::clone(&head));
-        for n in 2..=10 {
+        let mut map: LabelNodeMap = HashMap::new();
+        for n in 1..=11 {
             let inserted = list.push_back(n);
             map.insert(n, Rc::clone(&inserted));
         }
-        let inserted = list.push_back(11);
-        map.insert(11, Rc::clone(&inserted));
-        inserted.borrow_mut().set_next(&head);
-
         (list, map)
     }
 
+    fn values_from(list: &List<u32>, node: &NodeRef<u32>) -> Vec<u32> {
+        list.iter_from(node).map(|node| node.borrow().elem).collect()
+    }
+
+    fn values(list: &List<u32>) -> Vec<u32> {
+        list.head.as_ref().map_or(vec![], |head| values_from(list, head))
+    }
+
     #[test]
-    fn print_with_map() {
+    fn iterate_from_a_node() {
         let (list, map) = setup_map();
-        print_list_items(Rc::clone(list.head.as_ref().unwrap()), 11);
-        println!("From 5");
-        print_list_items(Rc::clone(map.get(&5).as_ref().unwrap()), 11);
+        assert_eq!(values_from(&list, &map[&5]), vec![5, 6, 7, 8, 9, 10, 11, 1, 2, 3, 4]);
+        assert_eq!(list.tail().unwrap().borrow().elem, 11);
     }
 
     #[test]
     fn take_center_out() {
-        let (_, map) = setup_map();
-        let to_remove_after = Rc::clone(map.get(&4).as_ref().unwrap());
-        let removed_head = Node::take_three_out(to_remove_after);
-        println!("With 3 removed");
-        print_list_items(Rc::clone(map.get(&1).as_ref().unwrap()), 20);
-        println!("Just the 3 that were removed");
-        print_list_items(Rc::clone(&removed_head), 3);
+        let (mut list, map) = setup_map();
+        let removed = list.remove_after(&map[&4], 3).unwrap();
+        assert_eq!(values(&list), vec![1, 2, 3, 4, 8, 9, 10, 11]);
+        assert_eq!(
+            removed.iter().map(|node| node.borrow().elem).collect::<Vec<_>>(),
+            vec![5, 6, 7]
+        );
 
         // Let's put them back in after 11.
-        let to_insert_after = Rc::clone(map.get(&11).as_ref().unwrap());
-        to_insert_after.borrow_mut().insert_after(removed_head);
-        println!("After the re-insertion");
-        print_list_items(Rc::clone(map.get(&1).as_ref().unwrap()), 11);
+        list.splice_after(&map[&11], removed);
+        assert_eq!(values(&list), vec![1, 2, 3, 4, 8, 9, 10, 11, 5, 6, 7]);
+        assert_eq!(list.len, 11);
+        assert_eq!(map[&7].borrow().next().unwrap().borrow().elem, 1);
+        assert_eq!(list.tail().unwrap().borrow().elem, 7);
+    }
+
+    #[test]
+    fn remove_wrapping_past_the_head() {
+        let (mut list, map) = setup_map();
+        let removed = list.remove_after(&map[&10], 3).unwrap();
+        assert_eq!(values(&list), vec![3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(list.tail().unwrap().borrow().elem, 10);
+        list.splice_after(&map[&3], removed);
+        assert_eq!(values(&list), vec![3, 11, 1, 2, 4, 5, 6, 7, 8, 9, 10]);
+
+        assert!(list.remove_after(&map[&3], 0).is_none());
+        assert!(list.remove_after(&map[&3], 11).is_none());
+        let all_but_one = list.remove_after(&map[&3], 10).unwrap();
+        assert_eq!(values(&list), vec![3]);
+        assert_eq!(all_but_one.iter().count(), 10);
+    }
+
+    #[test]
+    fn dropping_frees_every_node() {
+        let (list, map) = setup_map();
+        let node = Rc::clone(&map[&3]);
+        drop(map);
+        assert_eq!(Rc::strong_count(&node), 2);
+        drop(list);
+        assert_eq!(Rc::strong_count(&node), 1);
+        assert!(node.borrow().next().is_none());
+
+        let (mut list, map) = setup_map();
+        let segment = list.remove_after(&map[&1], 5).unwrap();
+        drop(list);
+        drop(segment);
+        assert!(map.values().all(|node| Rc::strong_count(node) == 1));
+
+        // Long enough to overflow the stack if dropped by recursion.
+        let mut list = List::new();
+        for n in 0..1_000_000 {
+            list.push_back(n);
+        }
+        assert_eq!(list.len, 1_000_000);
+        drop(list);
     }
 }
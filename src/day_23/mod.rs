@@ -1,177 +1,330 @@
+// The circular list the game used before the successor array, kept to play the old game in the
+// tests and check the new one against.
+#[cfg(test)]
 mod linked_list;
 
-use crate::day_23::linked_list::print_list_items;
-use hashbrown::HashMap;
-use std::cell::RefCell;
-use std::rc::Rc;
+use anyhow::anyhow;
+use itertools::Itertools;
+use std::fmt::Display;
+use std::{fmt, fs};
 
 pub fn solve_part_1() -> Result<(), ()> {
-    part_1("562893147");
+    let labels = str_to_vec(&fs::read_to_string("./inputs/day_23.txt").unwrap());
+    println!("{}", part_1(labels));
     Ok(())
 }
 
-fn part_1(input: &str) {
-    let problem = parse_input_part_1(input);
-    let result = run_game_as_list(problem, 100);
-    print_list_items(Rc::clone(result.get(&1).unwrap()), 9);
+fn part_1(labels: Vec<u32>) -> String {
+    let result = CupGame::new(labels).with_pick_up(3).with_moves(100).play().unwrap();
+    let labels = result.labels_from(1).skip(1).join("");
+    labels
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
-    part_2("562893147");
+    let labels = str_to_vec(&fs::read_to_string("./inputs/day_23.txt").unwrap());
+    println!("{}", part_2(labels));
     Ok(())
 }
 
-fn part_2(input: &str) {
-    let problem = parse_input_part_2(input);
-    let result = run_game_as_list(problem, 10_000_000);
-    print_list_items(Rc::clone(result.get(&1).unwrap()), 4);
+fn part_2(labels: Vec<u32>) -> u64 {
+    let result = CupGame::new(labels)
+        .with_cups(1_000_000)
+        .with_pick_up(3)
+        .with_moves(10_000_000)
+        .play()
+        .unwrap();
+    result.labels_from(1).skip(1).take(2).map(u64::from).product()
 }
 
-struct Problem {
-    starting_label: u32,
-    max_value: u32,
-    map: HashMap<u32, Rc<RefCell<linked_list::Node<u32>>>>,
+// The starting labels are followed by the next labels up, to make up the number of cups.
+#[derive(Debug, Clone)]
+struct CupGame {
+    labels: Vec<u32>,
+    cups: usize,
+    pick_up: usize,
+    moves: usize,
 }
 
-fn str_to_vec(input: &str) -> Vec<u32> {
-    input.chars().filter_map(|c| c.to_digit(10)).collect()
-}
+impl CupGame {
+    fn new(labels: Vec<u32>) -> CupGame {
+        CupGame {
+            cups: labels.len(),
+            labels,
+            pick_up: 3,
+            moves: 100,
+        }
+    }
 
-fn parse_input_part_1(input: &str) -> Problem {
-    let original = str_to_vec(input);
-    let starting_label = original[0];
-    let max_value = original.iter().max().copied().unwrap();
-    let map = setup_list_with_map_smaller(original);
-    Problem {
-        starting_label,
-        max_value,
-        map,
+    fn with_cups(self, cups: usize) -> CupGame {
+        CupGame { cups, ..self }
     }
-}
 
-fn parse_input_part_2(input: &str) -> Problem {
-    let original = str_to_vec(input);
-    let starting_label = original[0];
-    let map = setup_list_with_map_larger(original);
-    Problem {
-        starting_label,
-        max_value: 1_000_000,
-        map,
+    fn with_pick_up(self, pick_up: usize) -> CupGame {
+        CupGame { pick_up, ..self }
     }
-}
 
-fn get_new_label(current_label: u32, lifted: &[u32], max_value: u32) -> u32 {
-    let mut new_label = current_label - 1;
-    while new_label < 1 || lifted.contains(&new_label) {
-        if new_label == 0 {
-            new_label = max_value;
-        } else {
-            new_label -= 1;
+    fn with_moves(self, moves: usize) -> CupGame {
+        CupGame { moves, ..self }
+    }
+
+    // The circle before the first move.
+    fn start(&self) -> anyhow::Result<Circle> {
+        if self.labels.is_empty() {
+            return Err(anyhow!("There are no starting labels"));
+        }
+        if self.cups < self.labels.len() {
+            return Err(anyhow!(
+                "{} cups can't hold the {} starting labels",
+                self.cups,
+                self.labels.len()
+            ));
         }
+        let sorted: Vec<u32> = self.labels.iter().copied().sorted().collect();
+        if sorted.iter().zip(1..).any(|(&label, expected)| label != expected) {
+            return Err(anyhow!(
+                "The starting labels must be 1 to {} once each, found: {:?}",
+                self.labels.len(),
+                self.labels
+            ));
+        }
+        // A move needs a cup to place, and the destination has to be left in the circle.
+        if self.pick_up == 0 || self.pick_up + 2 > self.cups {
+            return Err(anyhow!("Can't pick up {} of {} cups", self.pick_up, self.cups));
+        }
+        let all_labels: Vec<u32> = self
+            .labels
+            .iter()
+            .copied()
+            .chain(self.labels.len() as u32 + 1..=self.cups as u32)
+            .collect();
+        Ok(Circle {
+            cups: Cups::new(&all_labels),
+            first: self.labels[0],
+            current: self.labels[0],
+            moves_made: 0,
+            picked_up: vec![0; self.pick_up],
+        })
     }
-    new_label
-}
 
-type LabelNodeMap = HashMap<u32, Rc<RefCell<linked_list::Node<u32>>>>;
+    fn state_after(&self, moves: usize) -> anyhow::Result<Circle> {
+        let mut circle = self.start()?;
+        for _ in 0..moves {
+            circle.play_move();
+        }
+        Ok(circle)
+    }
 
-fn setup_list_with_map_smaller(input: Vec<u32>) -> LabelNodeMap {
-    use linked_list::List;
-    use linked_list::Node;
-    let mut list: List<u32> = List::new();
-    let mut map: HashMap<u32, Rc<RefCell<Node<u32>>>> = HashMap::new();
-    // Need to join the end of the list:
-    let head = list.push_back(input[0]);
-    map.insert(input[0], Rc::clone(&head));
+    fn play(&self) -> anyhow::Result<Circle> {
+        self.state_after(self.moves)
+    }
+}
 
-    for item in &input[1..input.len() - 1] {
-        let to_insert = list.push_back(*item);
-        map.insert(*item, Rc::clone(&to_insert));
+// The circle as the label of the cup clockwise of each label, so that a move only rewrites three
+// entries. Labels run from 1, so the entry at 0 is unused.
+struct Cups {
+    next: Vec<u32>,
+}
+
+impl Cups {
+    fn new(labels: &[u32]) -> Cups {
+        let max_value = labels.iter().max().copied().unwrap_or(0);
+        let mut next = vec![0; max_value as usize + 1];
+        for (&label, &following) in labels.iter().zip(labels.iter().cycle().skip(1)) {
+            next[label as usize] = following;
+        }
+        Cups { next }
     }
 
-    let tail = list.push_back(*input.last().unwrap());
-    map.insert(*input.last().unwrap(), Rc::clone(&tail));
-    tail.borrow_mut().set_next(&head);
+    fn next(&self, label: u32) -> u32 {
+        self.next[label as usize]
+    }
 
-    map
+    fn max_value(&self) -> u32 {
+        (self.next.len() - 1) as u32
+    }
 }
 
-fn setup_list_with_map_larger(input: Vec<u32>) -> HashMap<u32, Rc<RefCell<linked_list::Node<u32>>>> {
-    use linked_list::List;
-    use linked_list::Node;
-    let mut list: List<u32> = List::new();
-    let mut map: HashMap<u32, Rc<RefCell<Node<u32>>>> = HashMap::new();
-    // Need to join the end of the list:
-    let head = list.push_back(input[0]);
-    map.insert(input[0], Rc::clone(&head));
+// A game in progress.
+struct Circle {
+    cups: Cups,
+    // The label the game started from, which the circle is shown from.
+    first: u32,
+    current: u32,
+    moves_made: usize,
+    // Reused between moves, so that a move doesn't allocate.
+    picked_up: Vec<u32>,
+}
 
-    for item in &input[1..] {
-        let to_insert = list.push_back(*item);
-        map.insert(*item, Rc::clone(&to_insert));
+impl Circle {
+    fn play_move(&mut self) {
+        let mut last = self.current;
+        for picked in self.picked_up.iter_mut() {
+            last = self.cups.next(last);
+            *picked = last;
+        }
+        let cups = &mut self.cups;
+        cups.next[self.current as usize] = cups.next(last);
+        let destination_label = get_new_label(self.current, &self.picked_up, cups.max_value());
+        cups.next[last as usize] = cups.next(destination_label);
+        cups.next[destination_label as usize] = self.picked_up[0];
+        self.current = cups.next(self.current);
+        self.moves_made += 1;
     }
 
-    for item in (input.iter().max().unwrap() + 1)..1000000 {
-        let to_insert = list.push_back(item);
-        map.insert(item, Rc::clone(&to_insert));
+    // Every label once, clockwise from `label`.
+    fn labels_from(&self, label: u32) -> impl Iterator<Item = u32> + '_ {
+        let mut current = label;
+        (0..self.cups.max_value()).map(move |_| {
+            let this = current;
+            current = self.cups.next(current);
+            this
+        })
     }
+}
 
-    let tail = list.push_back(1000000);
-    map.insert(1000000, Rc::clone(&tail));
-    tail.borrow_mut().set_next(&head);
-    map
+// In the puzzle's format: clockwise from the first starting label, with the current cup in brackets.
+impl Display for Circle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels = self.labels_from(self.first).map(|label| {
+            if label == self.current {
+                format!("({})", label)
+            } else {
+                label.to_string()
+            }
+        });
+        write!(f, "after {} moves: {}", self.moves_made, labels.format(" "))
+    }
 }
 
-fn get_values_of_removed(node: Rc<RefCell<linked_list::Node<u32>>>) -> Vec<u32> {
-    let mut current_node = node;
-    (0..3)
-        .map(|_| {
-            let element = current_node.borrow().elem;
-            let next_node = {
-                let borrowed_current = current_node.borrow();
-                Rc::clone(borrowed_current.next.as_ref().unwrap())
-            };
-            current_node = next_node;
-            element
-        })
-        .collect()
+fn str_to_vec(input: &str) -> Vec<u32> {
+    input.chars().filter_map(|c| c.to_digit(10)).collect()
 }
 
-fn run_game_as_list(problem: Problem, moves: usize) -> HashMap<u32, Rc<RefCell<linked_list::Node<u32>>>> {
-    use linked_list::Node;
-    let Problem {
-        map,
-        starting_label: mut current_label,
-        max_value,
-    } = problem;
-
-    for i in 0..moves {
-        if i % 1000000 == 0 {
-            println!("at {}", i);
-        }
-        let current_cup = Rc::clone(map.get(&current_label).unwrap());
-        let removed = Node::take_three_out(Rc::clone(&current_cup));
-        let values_of_removed: Vec<u32> = get_values_of_removed(Rc::clone(&removed));
-        let destination_label = get_new_label(current_label, &values_of_removed, max_value);
-        let insert_after = Rc::clone(map.get(&destination_label).unwrap());
-        insert_after.borrow_mut().insert_after(removed);
-        current_label = {
-            let label = current_cup.borrow().next.as_ref().unwrap().borrow().elem;
-            label
+fn get_new_label(current_label: u32, lifted: &[u32], max_value: u32) -> u32 {
+    let mut new_label = current_label - 1;
+    while new_label < 1 || lifted.contains(&new_label) {
+        if new_label == 0 {
+            new_label = max_value;
+        } else {
+            new_label -= 1;
         }
     }
-    map
+    new_label
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::day_23::linked_list::{List, NodeRef};
+    use hashbrown::HashMap;
+    use std::rc::Rc;
+    use std::time::Instant;
 
     #[test]
     fn test_game() {
-        part_1("389125467");
+        assert_eq!(part_1(str_to_vec("389125467")), "67384529");
+        let game = CupGame::new(str_to_vec("389125467")).with_moves(10);
+        assert_eq!(game.play().unwrap().labels_from(1).join(""), "192658374");
     }
 
     #[test]
     fn test_part_2() {
-        part_2("389125467");
+        assert_eq!(part_2(str_to_vec("389125467")), 149245887792);
+    }
+
+    #[test]
+    fn test_state_after_moves() {
+        let game = CupGame::new(str_to_vec("389125467"));
+        assert_eq!(
+            game.state_after(0).unwrap().to_string(),
+            "after 0 moves: (3) 8 9 1 2 5 4 6 7"
+        );
+        assert_eq!(
+            game.state_after(1).unwrap().to_string(),
+            "after 1 moves: 3 (2) 8 9 1 5 4 6 7"
+        );
+        assert_eq!(
+            game.state_after(10).unwrap().to_string(),
+            "after 10 moves: 3 7 4 1 9 2 6 5 (8)"
+        );
+    }
+
+    #[test]
+    fn test_configured_games() {
+        let game = CupGame::new(str_to_vec("3124")).with_cups(6).with_pick_up(1);
+        let circle = game.start().unwrap();
+        assert_eq!(circle.labels_from(3).collect::<Vec<_>>(), vec![3, 1, 2, 4, 5, 6]);
+        // 3 picks up 1, and the destination is 2.
+        assert_eq!(game.state_after(1).unwrap().to_string(), "after 1 moves: 3 (2) 1 4 5 6");
+        // Picking up all but two cups leaves the destination next to the current cup.
+        let game = CupGame::new(vec![1, 2, 3, 4]).with_pick_up(2);
+        assert_eq!(game.state_after(1).unwrap().to_string(), "after 1 moves: 1 (4) 2 3");
+    }
+
+    #[test]
+    fn test_invalid_games() {
+        assert!(CupGame::new(vec![]).start().is_err());
+        assert!(CupGame::new(vec![1, 2, 4]).start().is_err());
+        assert!(CupGame::new(vec![1, 2, 2]).start().is_err());
+        assert!(CupGame::new(vec![1, 2, 3]).with_cups(2).start().is_err());
+        assert!(CupGame::new(vec![1, 2, 3]).with_pick_up(2).start().is_err());
+        assert!(CupGame::new(vec![1, 2, 3]).with_pick_up(1).start().is_ok());
+        let error = CupGame::new(vec![1, 2, 3]).with_pick_up(0).start().err().unwrap();
+        assert_eq!(error.to_string(), "Can't pick up 0 of 3 cups");
+    }
+
+    // The game as it was played on the linked list, to benchmark against. Returns the labels
+    // clockwise from 1.
+    fn run_reference_game(labels: &[u32], moves: usize) -> Vec<u32> {
+        let mut list: List<u32> = List::new();
+        let mut map: HashMap<u32, NodeRef<u32>> = HashMap::new();
+        for &label in labels {
+            map.insert(label, list.push_back(label));
+        }
+        let max_value = labels.iter().max().copied().unwrap();
+        let mut current_label = labels[0];
+        for _ in 0..moves {
+            let current_cup = Rc::clone(&map[&current_label]);
+            let removed = list.remove_after(&current_cup, 3).unwrap();
+            let values_of_removed: Vec<u32> = removed.iter().map(|node| node.borrow().elem).collect();
+            let destination_label = get_new_label(current_label, &values_of_removed, max_value);
+            list.splice_after(&map[&destination_label], removed);
+            current_label = current_cup.borrow().next().unwrap().borrow().elem;
+        }
+        list.iter_from(&map[&1]).map(|node| node.borrow().elem).collect()
+    }
+
+    #[test]
+    fn test_matches_reference_game() {
+        let labels: Vec<u32> = str_to_vec("389125467").into_iter().chain(10..=50).collect();
+        let cups = CupGame::new(str_to_vec("389125467"))
+            .with_cups(50)
+            .state_after(1000)
+            .unwrap();
+        assert_eq!(
+            cups.labels_from(1).collect::<Vec<_>>(),
+            run_reference_game(&labels, 1000)
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_million_cups() {
+        let labels: Vec<u32> = str_to_vec("389125467").into_iter().chain(10..=1_000_000).collect();
+        for &moves in [100_000, 1_000_000].iter() {
+            let start = Instant::now();
+            run_reference_game(&labels, moves);
+            let reference = start.elapsed();
+            let start = Instant::now();
+            CupGame::new(str_to_vec("389125467"))
+                .with_cups(1_000_000)
+                .state_after(moves)
+                .unwrap();
+            let successors = start.elapsed();
+            println!(
+                "{} moves: linked list {:?}, successor array {:?}",
+                moves, reference, successors
+            );
+        }
     }
 }
@@ -0,0 +1,330 @@
+use std::ops::{Add, Mul, Sub};
+
+// A hex on a grid of pointy-topped hexes, in axial coordinates: `q` counts east along a row and
+// `r` counts rows to the south, so each row starts half a hex further west than the one above.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+// The same hex in cube coordinates, where `x + y + z == 0` and each axis is one of the three
+// directions a line of hexes can run.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Cube {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
+pub enum Direction {
+    NE,
+    E,
+    SE,
+    SW,
+    W,
+    NW,
+}
+
+impl Direction {
+    // Clockwise from north east.
+    pub const ALL: [Direction; 6] = [
+        Direction::NE,
+        Direction::E,
+        Direction::SE,
+        Direction::SW,
+        Direction::W,
+        Direction::NW,
+    ];
+
+    pub fn offset(self) -> Hex {
+        match self {
+            Direction::NE => Hex::new(1, -1),
+            Direction::E => Hex::new(1, 0),
+            Direction::SE => Hex::new(0, 1),
+            Direction::SW => Hex::new(-1, 1),
+            Direction::W => Hex::new(-1, 0),
+            Direction::NW => Hex::new(0, -1),
+        }
+    }
+}
+
+impl Hex {
+    pub const ORIGIN: Hex = Hex { q: 0, r: 0 };
+
+    pub const fn new(q: i32, r: i32) -> Hex {
+        Hex { q, r }
+    }
+
+    // The third cube coordinate, which axial coordinates leave out.
+    pub fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub fn to_cube(self) -> Cube {
+        Cube {
+            x: self.q,
+            y: self.s(),
+            z: self.r,
+        }
+    }
+
+    pub fn from_cube(cube: Cube) -> Hex {
+        debug_assert_eq!(cube.x + cube.y + cube.z, 0);
+        Hex::new(cube.x, cube.z)
+    }
+
+    pub fn neighbor(self, direction: Direction) -> Hex {
+        self + direction.offset()
+    }
+
+    pub fn neighbors(self) -> [Hex; 6] {
+        let mut neighbors = [self; 6];
+        for (neighbor, direction) in neighbors.iter_mut().zip(Direction::ALL.iter()) {
+            *neighbor = self.neighbor(*direction);
+        }
+        neighbors
+    }
+
+    // The number of steps from the origin.
+    #[allow(dead_code)]
+    pub fn length(self) -> i32 {
+        (self.q.abs() + self.r.abs() + self.s().abs()) / 2
+    }
+
+    #[allow(dead_code)]
+    pub fn distance(self, other: Hex) -> i32 {
+        (self - other).length()
+    }
+
+    // The hexes exactly `radius` steps from `self`, clockwise from the one furthest south west.
+    #[allow(dead_code)]
+    pub fn ring(self, radius: i32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let mut results = Vec::with_capacity(6 * radius as usize);
+        let mut hex = self + Direction::SW.offset() * radius;
+        for direction in Direction::ALL.iter().cycle().skip(5).take(6) {
+            for _ in 0..radius {
+                results.push(hex);
+                hex = hex.neighbor(*direction);
+            }
+        }
+        results
+    }
+
+    // Every hex within `radius` steps of `self`, ring by ring outwards.
+    #[allow(dead_code)]
+    pub fn spiral(self, radius: i32) -> Vec<Hex> {
+        (0..=radius).flat_map(|ring| self.ring(ring)).collect()
+    }
+
+    // The hexes a straight line from `self` to `other` passes through, including both ends.
+    #[allow(dead_code)]
+    pub fn line_to(self, other: Hex) -> Vec<Hex> {
+        let steps = self.distance(other);
+        if steps == 0 {
+            return vec![self];
+        }
+        let (from, to) = (self.to_cube(), other.to_cube());
+        // Nudged off the hex edges, so that a line running along one always rounds the same way.
+        let lerp = |a: i32, b: i32, t: f64, nudge: f64| a as f64 + nudge + (b - a) as f64 * t;
+        (0..=steps)
+            .map(|step| {
+                let t = step as f64 / steps as f64;
+                round_cube(
+                    lerp(from.x, to.x, t, 1e-6),
+                    lerp(from.y, to.y, t, 2e-6),
+                    lerp(from.z, to.z, t, -3e-6),
+                )
+            })
+            .collect()
+    }
+
+    // Turned `sixths` of a turn clockwise about the origin.
+    #[allow(dead_code)]
+    pub fn rotated_clockwise(self, sixths: usize) -> Hex {
+        let mut cube = self.to_cube();
+        for _ in 0..sixths % 6 {
+            cube = Cube {
+                x: -cube.z,
+                y: -cube.x,
+                z: -cube.y,
+            };
+        }
+        Hex::from_cube(cube)
+    }
+
+    // Mirrored in the line through the origin where `q` is constant, swapping the other two axes.
+    #[allow(dead_code)]
+    pub fn reflected_q(self) -> Hex {
+        let cube = self.to_cube();
+        Hex::from_cube(Cube {
+            x: cube.x,
+            y: cube.z,
+            z: cube.y,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn reflected_r(self) -> Hex {
+        let cube = self.to_cube();
+        Hex::from_cube(Cube {
+            x: cube.y,
+            y: cube.x,
+            z: cube.z,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn reflected_s(self) -> Hex {
+        let cube = self.to_cube();
+        Hex::from_cube(Cube {
+            x: cube.z,
+            y: cube.y,
+            z: cube.x,
+        })
+    }
+}
+
+// The hex containing a fractional cube position: round each axis, then fix up the one that moved
+// furthest so that the coordinates still sum to zero.
+fn round_cube(x: f64, y: f64, z: f64) -> Hex {
+    let (mut rx, mut ry, mut rz) = (x.round(), y.round(), z.round());
+    let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+    if dx > dy && dx > dz {
+        rx = -ry - rz;
+    } else if dy > dz {
+        ry = -rx - rz;
+    } else {
+        rz = -rx - ry;
+    }
+    Hex::from_cube(Cube {
+        x: rx as i32,
+        y: ry as i32,
+        z: rz as i32,
+    })
+}
+
+impl From<Cube> for Hex {
+    fn from(cube: Cube) -> Hex {
+        Hex::from_cube(cube)
+    }
+}
+
+impl From<Hex> for Cube {
+    fn from(hex: Hex) -> Cube {
+        hex.to_cube()
+    }
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, times: i32) -> Hex {
+        Hex::new(self.q * times, self.r * times)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hashbrown::HashSet;
+
+    #[test]
+    fn test_coordinates() {
+        let hex = Hex::new(2, -5);
+        assert_eq!(hex.to_cube(), Cube { x: 2, y: 3, z: -5 });
+        assert_eq!(Hex::from(Cube::from(hex)), hex);
+        assert_eq!(Direction::NE.offset().to_cube(), Cube { x: 1, y: 0, z: -1 });
+    }
+
+    #[test]
+    fn test_neighbors_and_distance() {
+        let hex = Hex::new(3, -1);
+        for (i, neighbor) in hex.neighbors().iter().enumerate() {
+            assert_eq!(hex.distance(*neighbor), 1);
+            assert_eq!(neighbor.neighbor(Direction::ALL[(i + 3) % 6]), hex);
+        }
+        assert_eq!(Hex::ORIGIN.distance(Hex::new(3, -1)), 3);
+        assert_eq!(Hex::new(-2, 4).distance(Hex::new(3, -1)), 5);
+        assert_eq!(Hex::new(1, 1).length(), 2);
+    }
+
+    #[test]
+    fn test_rings_and_spirals() {
+        let center = Hex::new(1, 2);
+        assert_eq!(center.ring(0), vec![center]);
+        for radius in 1..5 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|hex| hex.distance(center) == radius));
+            assert_eq!(ring.iter().collect::<HashSet<_>>().len(), ring.len());
+        }
+        assert_eq!(
+            Hex::ORIGIN.ring(1),
+            vec![
+                Hex::new(-1, 1),
+                Hex::new(-1, 0),
+                Hex::new(0, -1),
+                Hex::new(1, -1),
+                Hex::new(1, 0),
+                Hex::new(0, 1)
+            ]
+        );
+        let spiral = center.spiral(3);
+        assert_eq!(spiral.len(), 1 + 6 + 12 + 18);
+        assert_eq!(spiral[0], center);
+        assert!(spiral
+            .windows(2)
+            .all(|w| w[0].distance(center) <= w[1].distance(center)));
+    }
+
+    #[test]
+    fn test_lines() {
+        let (from, to) = (Hex::new(-2, 0), Hex::new(3, -2));
+        let line = from.line_to(to);
+        assert_eq!(line.len(), from.distance(to) as usize + 1);
+        assert_eq!((line[0], *line.last().unwrap()), (from, to));
+        assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+        assert_eq!(
+            Hex::ORIGIN.line_to(Hex::new(3, 0)),
+            vec![Hex::new(0, 0), Hex::new(1, 0), Hex::new(2, 0), Hex::new(3, 0)]
+        );
+        assert_eq!(from.line_to(from), vec![from]);
+    }
+
+    #[test]
+    fn test_rotation_and_reflection() {
+        let hex = Hex::new(2, -1);
+        assert_eq!(Direction::NE.offset().rotated_clockwise(1), Direction::E.offset());
+        assert_eq!(hex.rotated_clockwise(6), hex);
+        assert_eq!(hex.rotated_clockwise(3), hex * -1);
+        assert_eq!(hex.rotated_clockwise(2).length(), hex.length());
+
+        assert_eq!(hex.reflected_q().reflected_q(), hex);
+        assert_eq!(Hex::new(2, -3).reflected_q(), Hex::new(2, 1));
+        assert_eq!(hex.reflected_q().q, hex.q);
+        assert_eq!(hex.reflected_r().r, hex.r);
+        assert_eq!(hex.reflected_s().s(), hex.s());
+        assert_eq!(Direction::E.offset().reflected_r(), Direction::W.offset());
+    }
+}
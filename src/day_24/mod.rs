@@ -1,9 +1,15 @@
+mod hex;
+mod path;
+mod svg;
+
+use crate::day_24::hex::{Direction, Hex};
+use crate::day_24::svg::{Bounds, SvgOptions};
 use hashbrown::HashMap;
-use itertools::Itertools;
 use std::fs;
+use std::path::Path;
 
 pub fn solve_part_1() -> Result<(), ()> {
-    let input = parse_input(&fs::read_to_string("./inputs/day_24").unwrap());
+    let input = parse_input(&fs::read_to_string("./inputs/day_24").unwrap()).map_err(|e| println!("{}", e))?;
     println!("{}", part_1(input));
     Ok(())
 }
@@ -14,7 +20,7 @@ fn part_1(input: Vec<Vec<Direction>>) -> usize {
 }
 
 #[inline]
-fn count_black_tiles_on_floor(floor: &HashMap<(i32, i32), Tile>) -> usize {
+fn count_black_tiles_on_floor(floor: &HashMap<Hex, Tile>) -> usize {
     floor
         .iter()
         .filter(|(_, v)| matches!(v.state, TileColor::Black))
@@ -22,56 +28,59 @@ fn count_black_tiles_on_floor(floor: &HashMap<(i32, i32), Tile>) -> usize {
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
-    let input = parse_input(&fs::read_to_string("./inputs/day_24").unwrap());
+    let input = parse_input(&fs::read_to_string("./inputs/day_24").unwrap()).map_err(|e| println!("{}", e))?;
     println!("{}", part_2(input, 100));
     Ok(())
 }
 
-fn part_2(input: Vec<Vec<Direction>>, iterations: usize) -> usize {
-    let floor = set_tiles_from_directions(input);
-    count_black_tiles_on_floor(&run_game_of_life(floor, iterations))
-}
-
-fn str_to_direction(input: &str) -> Direction {
-    match input {
-        "ne" => Direction::NE,
-        "e" => Direction::E,
-        "se" => Direction::SE,
-        "sw" => Direction::SW,
-        "w" => Direction::W,
-        "nw" => Direction::NW,
-        _ => panic!("Unknown direction passed into str_to_direction"),
+// Plays part 2 as usual, also writing the floor after each day to `frames_dir` as an SVG frame.
+pub fn solve_part_2_with_frames(frames_dir: &str, show_coordinates: bool) -> Result<(), ()> {
+    let input = parse_input(&fs::read_to_string("./inputs/day_24").unwrap()).map_err(|e| println!("{}", e))?;
+    let options = SvgOptions {
+        show_coordinates,
+        ..SvgOptions::default()
+    };
+    let mut floors = vec![];
+    run_game_of_life_with(set_tiles_from_directions(input), 100, |_, floor| {
+        floors.push(floor.clone())
+    });
+    println!("{}", count_black_tiles_on_floor(floors.last().unwrap()));
+    let frames = render_frames(&floors, &options);
+    fs::create_dir_all(frames_dir).map_err(|e| println!("{}", e))?;
+    for (day, frame) in frames.iter().enumerate() {
+        let path = Path::new(frames_dir).join(format!("day_{:03}.svg", day));
+        fs::write(path, frame).map_err(|e| println!("{}", e))?;
     }
+    println!("Wrote {} frames to {}", frames.len(), frames_dir);
+    Ok(())
 }
 
-fn line_to_tile_directions(line: &str) -> Vec<Direction> {
-    line.chars()
-        .batching(|it| match it.next() {
-            None => None,
-            Some(c) => match c {
-                'n' | 's' => Some([c, it.next().unwrap()].iter().join("")),
-                _ => Some(c.to_string()),
-            },
-        })
-        .map(|individual| str_to_direction(&individual))
+// One SVG per floor, all drawn over the same area so that they line up as an animation.
+fn render_frames(floors: &[HashMap<Hex, Tile>], options: &SvgOptions) -> Vec<String> {
+    let bounds = floors
+        .iter()
+        .map(Bounds::of_floor)
+        .reduce(Bounds::union)
+        .unwrap_or_else(|| Bounds::of_hexes(std::iter::empty()));
+    floors
+        .iter()
+        .map(|floor| svg::floor_to_svg(floor, bounds, options))
         .collect()
 }
 
-fn parse_input(input: &str) -> Vec<Vec<Direction>> {
-    input.split('\n').map(line_to_tile_directions).collect()
+fn part_2(input: Vec<Vec<Direction>>, iterations: usize) -> usize {
+    let floor = set_tiles_from_directions(input);
+    count_black_tiles_on_floor(&run_game_of_life(floor, iterations))
 }
 
-fn trundle_tile(directions: Vec<Direction>) -> (i32, i32) {
-    directions.iter().fold((0, 0), |acc, direction| {
-        let to_add = direction.direction_to_offset();
-        (acc.0 + to_add.0, acc.1 + to_add.1)
-    })
+fn parse_input(input: &str) -> anyhow::Result<Vec<Vec<Direction>>> {
+    path::parse_paths(input)
 }
 
-fn set_tiles_from_directions(directions: Vec<Vec<Direction>>) -> HashMap<(i32, i32), Tile> {
+fn set_tiles_from_directions(directions: Vec<Vec<Direction>>) -> HashMap<Hex, Tile> {
     let mut tile_locations = HashMap::new();
     for (_, tile) in directions.into_iter().enumerate() {
-        let result = trundle_tile(tile);
+        let result = path::destination(&tile);
         let mut entry = tile_locations
             .entry(result) // Get the tile to flip
             .or_insert(Tile {
@@ -83,7 +92,7 @@ fn set_tiles_from_directions(directions: Vec<Vec<Direction>>) -> HashMap<(i32, i
     tile_locations
 }
 
-fn adjacent_black_tiles(tile: Tile, floor: &HashMap<(i32, i32), Tile>) -> usize {
+fn adjacent_black_tiles(tile: Tile, floor: &HashMap<Hex, Tile>) -> usize {
     neighbors(tile).into_iter().fold(0, |acc, pos_to_check| {
         let neighbor_color = floor
             .get(&pos_to_check)
@@ -97,16 +106,11 @@ fn adjacent_black_tiles(tile: Tile, floor: &HashMap<(i32, i32), Tile>) -> usize
     })
 }
 
-fn neighbors(tile: Tile) -> Vec<(i32, i32)> {
-    let adjacent_offsets = Direction::adjacent_offsets();
-    let current_position = tile.location;
-    adjacent_offsets
-        .iter()
-        .map(|offset| (current_position.0 + offset.0, current_position.1 + offset.1))
-        .collect()
+fn neighbors(tile: Tile) -> Vec<Hex> {
+    tile.location.neighbors().to_vec()
 }
 
-fn new_tile_state(tile: Tile, floor: &HashMap<(i32, i32), Tile>) -> TileColor {
+fn new_tile_state(tile: Tile, floor: &HashMap<Hex, Tile>) -> TileColor {
     let adjacent_black_tiles = adjacent_black_tiles(tile, floor);
     match tile.state {
         TileColor::Black => {
@@ -126,59 +130,19 @@ fn new_tile_state(tile: Tile, floor: &HashMap<(i32, i32), Tile>) -> TileColor {
     }
 }
 
-fn print_floor(floor: &HashMap<(i32, i32), Tile>) {
-    // println!("{:#?}", floor);
-    let y_extent = (
-        floor.iter().map(|(k, _)| k.0).min().unwrap(),
-        floor.iter().map(|(k, _)| k.0).max().unwrap(),
-    );
-    let x_extent = (
-        floor.iter().map(|(k, _)| k.1).min().unwrap(),
-        floor.iter().map(|(k, _)| k.1).max().unwrap(),
-    );
-    print!("          ");
-    let x_range_to_print = (-x_extent.1 - 1)..=(x_extent.1 + 1);
-    let y_range_to_print = (-y_extent.1 - 1)..=(y_extent.1 + 1);
-    for x in x_range_to_print.clone() {
-        if x % 2 == 0 {
-            print!("{} ", x.abs());
-        }
-    }
-
-    for y in y_range_to_print {
-        println!();
-        print!("y: {:2}  |  ", y);
-        if y.abs() % 2 == 1 {
-            print!(" ");
-        }
-        let x_mod_to_print = y.abs() % 2;
-        for x in x_range_to_print.clone() {
-            if x.abs() % 2 != x_mod_to_print {
-                continue;
-            }
-            let to_print = floor.get(&(y, x));
-            let to_print = match to_print {
-                None => ".",
-                Some(&Tile { state: color, .. }) => match color {
-                    TileColor::Black => "b",
-                    TileColor::White => "W",
-                },
-            };
-            print!("{} ", to_print);
-        }
-    }
-    println!();
+fn run_game_of_life(floor: HashMap<Hex, Tile>, iterations: usize) -> HashMap<Hex, Tile> {
+    run_game_of_life_with(floor, iterations, |_, _| {})
 }
 
-const DEBUG: bool = false;
-fn run_game_of_life(mut floor: HashMap<(i32, i32), Tile>, iterations: usize) -> HashMap<(i32, i32), Tile> {
-    if DEBUG {
-        println!("Starting State");
-        print_floor(&floor);
-    }
-
+// Runs the game, calling `on_frame` with the starting floor as frame 0 and then after each iteration.
+fn run_game_of_life_with<F: FnMut(usize, &HashMap<Hex, Tile>)>(
+    mut floor: HashMap<Hex, Tile>,
+    iterations: usize,
+    mut on_frame: F,
+) -> HashMap<Hex, Tile> {
+    on_frame(0, &floor);
     for i in 0..iterations {
-        let mut new_floor: HashMap<(i32, i32), Tile> = HashMap::new();
+        let mut new_floor: HashMap<Hex, Tile> = HashMap::new();
         for (position, tile) in floor.iter().filter(|(_, v)| v.state == TileColor::Black) {
             let mut tiles_to_set = vec![*position];
             tiles_to_set.append(&mut neighbors(*tile));
@@ -203,18 +167,14 @@ fn run_game_of_life(mut floor: HashMap<(i32, i32), Tile>, iterations: usize) ->
             }
         }
         floor = new_floor;
-        println!("Iteration: {}", i);
-        if DEBUG {
-            println!("After iteration {}", i);
-            print_floor(&floor);
-        }
+        on_frame(i + 1, &floor);
     }
     floor
 }
 
 #[derive(Debug, Copy, Clone)]
 struct Tile {
-    location: (i32, i32),
+    location: Hex,
     state: TileColor,
 }
 
@@ -233,44 +193,13 @@ impl TileColor {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
-enum Direction {
-    NE,
-    E,
-    SE,
-    SW,
-    W,
-    NW,
-}
-
-impl Direction {
-    fn direction_to_offset(&self) -> (i32, i32) {
-        match self {
-            Self::NE => (1, 1),
-            Self::E => (0, 2),
-            Self::SE => (-1, 1),
-            Self::SW => (-1, -1),
-            Self::W => (0, -2),
-            Self::NW => (1, -1),
-        }
-    }
-
-    #[inline]
-    fn adjacent_offsets() -> Vec<(i32, i32)> {
-        [Self::NE, Self::E, Self::SE, Self::SW, Self::W, Self::NW]
-            .iter()
-            .map(Direction::direction_to_offset)
-            .collect()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_part_1() {
-        let input = parse_input(&fs::read_to_string("./test_inputs/day_24").unwrap());
+        let input = parse_input(&fs::read_to_string("./test_inputs/day_24").unwrap()).unwrap();
         assert_eq!(part_1(input), 10);
     }
     #[test]
@@ -287,13 +216,14 @@ mod tests {
             Direction::NW,
             Direction::SE,
         ];
-        assert_eq!(line_to_tile_directions("seswneswswsenwwnwse"), correct);
-        assert_eq!(trundle_tile(correct), (-3, -3));
+        assert_eq!(parse_input("seswneswswsenwwnwse").unwrap(), vec![correct.clone()]);
+        assert_eq!(path::destination(&correct), Hex::new(-3, 3));
+        assert!(parse_input("esew\nnwwswee\nsnee").is_err());
     }
 
     #[test]
     fn test_part_2() {
-        let input = parse_input(&fs::read_to_string("./test_inputs/day_24").unwrap());
+        let input = parse_input(&fs::read_to_string("./test_inputs/day_24").unwrap()).unwrap();
         assert_eq!(part_2(input.clone(), 1), 15);
         assert_eq!(part_2(input.clone(), 3), 25);
         assert_eq!(part_2(input.clone(), 50), 566);
@@ -304,68 +234,144 @@ mod tests {
     fn test_parts() {
         let tiles = vec![
             Tile {
-                location: (0, 2),
+                location: Hex::new(1, 0),
                 state: TileColor::White,
             },
             Tile {
-                location: (-1, -1),
+                location: Hex::new(-1, 1),
                 state: TileColor::Black,
             },
             Tile {
-                location: (0, 2),
+                location: Hex::new(1, 0),
                 state: TileColor::White,
             },
             Tile {
-                location: (1, -1),
+                location: Hex::new(0, -1),
                 state: TileColor::Black,
             },
         ];
-        let floor: HashMap<(i32, i32), Tile> = tiles.into_iter().map(|tile| (tile.location, tile)).collect();
+        let floor: HashMap<Hex, Tile> = tiles.into_iter().map(|tile| (tile.location, tile)).collect();
         let tile_to_check = Tile {
-            location: (0, 0),
+            location: Hex::ORIGIN,
             state: TileColor::White,
         };
         assert_eq!(adjacent_black_tiles(tile_to_check, &floor), 2);
         assert_eq!(new_tile_state(tile_to_check, &floor), TileColor::Black);
     }
 
+    // Every corner of every tile lies in the viewBox, give or take the two decimal places written.
+    fn assert_polygons_in_view_box(svg: &str) {
+        let attribute = |line: &str, name: &str| -> Vec<f64> {
+            let start = line.find(&format!("{}=\"", name)).unwrap() + name.len() + 2;
+            let end = start + line[start..].find('"').unwrap();
+            line[start..end]
+                .split([' ', ','])
+                .map(|number| number.parse().unwrap())
+                .collect()
+        };
+        let view_box = attribute(svg.lines().next().unwrap(), "viewBox");
+        let (left, top, right, bottom) = (
+            view_box[0],
+            view_box[1],
+            view_box[0] + view_box[2],
+            view_box[1] + view_box[3],
+        );
+        for line in svg.lines().filter(|line| line.starts_with("<polygon")) {
+            for point in attribute(line, "points").chunks(2) {
+                let (x, y) = (point[0], point[1]);
+                assert!(
+                    x >= left - 0.01 && x <= right + 0.01 && y >= top - 0.01 && y <= bottom + 0.01,
+                    "({}, {}) is outside {:?}",
+                    x,
+                    y,
+                    view_box
+                );
+            }
+        }
+    }
+
     #[test]
-    fn test_print_floor() {
+    fn test_floor_svg() {
         let tiles = vec![
             Tile {
-                location: (0, -2),
+                location: Hex::new(-1, 0),
                 state: TileColor::White,
             },
             Tile {
-                location: (-1, -1),
+                location: Hex::new(-1, 1),
                 state: TileColor::Black,
             },
             Tile {
-                location: (0, 2),
+                location: Hex::new(1, 0),
                 state: TileColor::White,
             },
             Tile {
-                location: (1, -1),
+                location: Hex::new(0, -1),
                 state: TileColor::Black,
             },
         ];
-        let floor: HashMap<(i32, i32), Tile> = tiles.into_iter().map(|tile| (tile.location, tile)).collect();
-        print_floor(&floor);
+        let floor: HashMap<Hex, Tile> = tiles.into_iter().map(|tile| (tile.location, tile)).collect();
+        let bounds = Bounds::of_floor(&floor);
+        let svg = svg::floor_to_svg(&floor, bounds, &SvgOptions::default());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polygon").count(), 4);
+        assert_eq!(svg.matches(r##"fill="#222222""##).count(), 2);
+        assert!(!svg.contains("<text"));
+        assert_polygons_in_view_box(&svg);
+
+        let options = SvgOptions {
+            show_coordinates: true,
+            ..SvgOptions::default()
+        };
+        let svg = svg::floor_to_svg(&floor, bounds, &options);
+        assert_eq!(svg.matches("<text").count(), 4);
+        assert!(svg.contains(">-1,0</text>"));
+    }
+
+    #[test]
+    fn test_game_frames() {
+        let floor: HashMap<Hex, Tile> = [Hex::new(-1, 1), Hex::new(0, 1)]
+            .iter()
+            .map(|&location| {
+                let tile = Tile {
+                    location,
+                    state: TileColor::Black,
+                };
+                (location, tile)
+            })
+            .collect();
+        let (mut days, mut floors) = (vec![], vec![]);
+        let last = run_game_of_life_with(floor, 3, |day, floor| {
+            days.push((day, count_black_tiles_on_floor(floor)));
+            floors.push(floor.clone());
+        });
+        assert_eq!(days[..2], [(0, 2), (1, 4)]);
+        assert_eq!(days.last(), Some(&(3, count_black_tiles_on_floor(&last))));
+
+        let frames = render_frames(&floors, &SvgOptions::default());
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].matches("<polygon").count(), 2);
+        let view_box = |frame: &String| frame.lines().next().unwrap().to_string();
+        assert!(frames.iter().all(|frame| view_box(frame) == view_box(&frames[0])));
+        for frame in frames.iter() {
+            assert_polygons_in_view_box(frame);
+        }
     }
 
     #[test]
     fn test_simple_game() {
         let tiles = vec![
             Tile {
-                location: (-1, -1),
+                location: Hex::new(-1, 1),
                 state: TileColor::Black,
             },
             Tile {
-                location: (-1, 1),
+                location: Hex::new(0, 1),
                 state: TileColor::Black,
             },
             Tile {
-                location: (0, 0),
+                location: Hex::ORIGIN,
                 state: TileColor::White,
             },
         ];
@@ -0,0 +1,146 @@
+use crate::day_24::hex::{Direction, Hex};
+use anyhow::{anyhow, Result};
+
+pub fn direction_name(direction: Direction) -> &'static str {
+    match direction {
+        Direction::NE => "ne",
+        Direction::E => "e",
+        Direction::SE => "se",
+        Direction::SW => "sw",
+        Direction::W => "w",
+        Direction::NW => "nw",
+    }
+}
+
+// One line of directions, e.g. "esenee", failing at the first character that doesn't start or
+// finish a direction. Columns count from 1.
+pub fn parse_path(line: &str) -> Result<Vec<Direction>> {
+    let mut path = vec![];
+    let mut chars = line.chars().enumerate();
+    while let Some((column, c)) = chars.next() {
+        let direction = match c {
+            'e' => Direction::E,
+            'w' => Direction::W,
+            'n' | 's' => match (c, chars.next()) {
+                ('n', Some((_, 'e'))) => Direction::NE,
+                ('n', Some((_, 'w'))) => Direction::NW,
+                ('s', Some((_, 'e'))) => Direction::SE,
+                ('s', Some((_, 'w'))) => Direction::SW,
+                (_, Some((column, other))) => {
+                    return Err(anyhow!(
+                        "Expected 'e' or 'w' after '{}' at column {}, found {:?}",
+                        c,
+                        column + 1,
+                        other
+                    ))
+                }
+                (_, None) => {
+                    return Err(anyhow!(
+                        "Expected 'e' or 'w' after '{}' at column {}, found the end of the line",
+                        c,
+                        column + 1
+                    ))
+                }
+            },
+            other => return Err(anyhow!("Unknown direction {:?} at column {}", other, column + 1)),
+        };
+        path.push(direction);
+    }
+    Ok(path)
+}
+
+// Every line of the input as a path, with errors saying which line they're on.
+pub fn parse_paths(input: &str) -> Result<Vec<Vec<Direction>>> {
+    input
+        .split('\n')
+        .enumerate()
+        .map(|(line, text)| parse_path(text).map_err(|e| anyhow!("Line {}: {}", line + 1, e)))
+        .collect()
+}
+
+#[allow(dead_code)]
+pub fn format_path(path: &[Direction]) -> String {
+    path.iter().map(|&direction| direction_name(direction)).collect()
+}
+
+pub fn destination(path: &[Direction]) -> Hex {
+    path.iter().fold(Hex::ORIGIN, |hex, &direction| hex.neighbor(direction))
+}
+
+// The canonical shortest path from the origin to `hex`: at most two directions, next to each
+// other on the compass, with all the steps in one grouped before the other in clockwise order
+// from north east.
+#[allow(dead_code)]
+pub fn path_to(hex: Hex) -> Vec<Direction> {
+    let (q, r) = (hex.q, hex.r);
+    let steps = if q >= 0 && r >= 0 {
+        [(Direction::E, q), (Direction::SE, r)]
+    } else if q <= 0 && r <= 0 {
+        [(Direction::W, -q), (Direction::NW, -r)]
+    } else if q > 0 {
+        // Heading north east, then either east or north west for the rest.
+        if q >= -r {
+            [(Direction::NE, -r), (Direction::E, q + r)]
+        } else {
+            [(Direction::NE, q), (Direction::NW, -r - q)]
+        }
+    } else if r >= -q {
+        [(Direction::SE, r + q), (Direction::SW, -q)]
+    } else {
+        [(Direction::SW, r), (Direction::W, -q - r)]
+    };
+    steps
+        .iter()
+        .flat_map(|&(direction, count)| (0..count).map(move |_| direction))
+        .collect()
+}
+
+// The shortest path that ends up on the same tile as `path`.
+#[allow(dead_code)]
+pub fn normalize(path: &[Direction]) -> Vec<Direction> {
+    path_to(destination(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsing_errors() {
+        assert_eq!(parse_path("").unwrap(), vec![]);
+        assert_eq!(
+            parse_path("nwwswee").unwrap(),
+            vec![Direction::NW, Direction::W, Direction::SW, Direction::E, Direction::E]
+        );
+        let error = |line: &str| parse_path(line).unwrap_err().to_string();
+        assert_eq!(error("eex"), "Unknown direction 'x' at column 3");
+        assert_eq!(error("enn"), "Expected 'e' or 'w' after 'n' at column 3, found 'n'");
+        assert_eq!(error("wns"), "Expected 'e' or 'w' after 'n' at column 3, found 's'");
+        assert_eq!(
+            error("es"),
+            "Expected 'e' or 'w' after 's' at column 2, found the end of the line"
+        );
+        assert_eq!(
+            parse_paths("esew\nnwwswee\nsee\nnn").unwrap_err().to_string(),
+            "Line 4: Expected 'e' or 'w' after 'n' at column 2, found 'n'"
+        );
+    }
+
+    #[test]
+    fn test_shortest_paths() {
+        for hex in Hex::ORIGIN.spiral(6) {
+            let path = path_to(hex);
+            assert_eq!(destination(&path), hex);
+            assert_eq!(path.len() as i32, hex.length());
+            assert_eq!(parse_path(&format_path(&path)).unwrap(), path);
+        }
+        assert_eq!(format_path(&path_to(Hex::new(3, -1))), "neee");
+        assert_eq!(format_path(&path_to(Hex::new(-1, 3))), "sesesw");
+
+        let path = parse_path("nwwswee").unwrap();
+        assert_eq!(format_path(&normalize(&path)), "");
+        let path = parse_path("seswneswswsenwwnwse").unwrap();
+        assert_eq!(format_path(&normalize(&path)), "swswsw");
+        assert_eq!(normalize(&normalize(&path)), normalize(&path));
+    }
+}
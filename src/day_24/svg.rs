@@ -0,0 +1,129 @@
+use crate::day_24::hex::Hex;
+use crate::day_24::{Tile, TileColor};
+use hashbrown::HashMap;
+use std::fmt::Write;
+
+const SQRT_3: f64 = 1.732_050_807_568_877_2;
+
+pub struct SvgOptions {
+    // The distance from a hex's center to its corners, in pixels.
+    pub hex_size: f64,
+    pub show_coordinates: bool,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        SvgOptions {
+            hex_size: 10.0,
+            show_coordinates: false,
+        }
+    }
+}
+
+// The smallest and largest `q` and `r` to draw, so that every frame of an animation can share them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    min: Hex,
+    max: Hex,
+}
+
+impl Bounds {
+    pub fn of_floor(floor: &HashMap<Hex, Tile>) -> Bounds {
+        Bounds::of_hexes(floor.keys())
+    }
+
+    pub fn of_hexes<'a, I: Iterator<Item = &'a Hex>>(hexes: I) -> Bounds {
+        let mut bounds: Option<Bounds> = None;
+        for hex in hexes {
+            bounds = Some(match bounds {
+                None => Bounds { min: *hex, max: *hex },
+                Some(bounds) => bounds.including(*hex),
+            })
+        }
+        bounds.unwrap_or(Bounds {
+            min: Hex::ORIGIN,
+            max: Hex::ORIGIN,
+        })
+    }
+
+    pub fn union(self, other: Bounds) -> Bounds {
+        self.including(other.min).including(other.max)
+    }
+
+    fn including(self, hex: Hex) -> Bounds {
+        Bounds {
+            min: Hex::new(self.min.q.min(hex.q), self.min.r.min(hex.r)),
+            max: Hex::new(self.max.q.max(hex.q), self.max.r.max(hex.r)),
+        }
+    }
+}
+
+// The center of a pointy-topped hex, in pixels.
+fn center(hex: Hex, size: f64) -> (f64, f64) {
+    (
+        size * SQRT_3 * (hex.q as f64 + hex.r as f64 / 2.0),
+        size * 1.5 * hex.r as f64,
+    )
+}
+
+fn corners(hex: Hex, size: f64) -> String {
+    let (x, y) = center(hex, size);
+    (0..6)
+        .map(|corner| {
+            let angle = (60.0 * corner as f64 - 30.0).to_radians();
+            format!("{:.2},{:.2}", x + size * angle.cos(), y + size * angle.sin())
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn floor_to_svg(floor: &HashMap<Hex, Tile>, bounds: Bounds, options: &SvgOptions) -> String {
+    let size = options.hex_size;
+    // Each row shifts half a hex right, so the leftmost center has the smallest `q` and `r`.
+    let left = center(bounds.min, size).0 - size;
+    let right = center(bounds.max, size).0 + size;
+    let top = center(bounds.min, size).1 - size;
+    let bottom = center(bounds.max, size).1 + size;
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{:.2} {:.2} {:.2} {:.2}">"#,
+        left,
+        top,
+        right - left,
+        bottom - top
+    )
+    .unwrap();
+    let mut tiles: Vec<&Tile> = floor.values().collect();
+    tiles.sort_by_key(|tile| (tile.location.r, tile.location.q));
+    for tile in tiles {
+        let (fill, text) = match tile.state {
+            TileColor::Black => ("#222222", "#ffffff"),
+            TileColor::White => ("#ffffff", "#222222"),
+        };
+        writeln!(
+            svg,
+            r##"<polygon points="{}" fill="{}" stroke="#999999" stroke-width="{:.2}"/>"##,
+            corners(tile.location, size),
+            fill,
+            size / 10.0
+        )
+        .unwrap();
+        if options.show_coordinates {
+            let (x, y) = center(tile.location, size);
+            writeln!(
+                svg,
+                r#"<text x="{:.2}" y="{:.2}" font-size="{:.2}" text-anchor="middle" dominant-baseline="middle" fill="{}">{},{}</text>"#,
+                x,
+                y,
+                size / 2.5,
+                text,
+                tile.location.q,
+                tile.location.r
+            )
+            .unwrap();
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -1,12 +1,18 @@
+mod modular;
+
+use crate::day_25::modular::{discrete_log, factorize, order, pow_mod, totient};
+use anyhow::{anyhow, Result};
+use std::fs;
+
 pub fn solve_part_1() -> Result<(), ()> {
-    let door_key = 8987316;
-    let (_, card_loop) = part_1((8987316, 14681524));
-    println!("{}", transform(door_key, card_loop));
+    let input = parse_input(&fs::read_to_string("./inputs/day_25.txt").unwrap()).map_err(|e| println!("{}", e))?;
+    let result = part_1(&input).map_err(|e| println!("{}", e))?;
+    println!("{}", result.encryption_key);
     Ok(())
 }
 
-fn part_1((door_key, card_key): (u64, u64)) -> (u64, u64) {
-    (transform_with_target(7, door_key), transform_with_target(7, card_key))
+fn part_1(input: &Input) -> Result<HandshakeResult> {
+    input.handshake.perform(input.card_key, input.door_key)
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
@@ -14,28 +20,136 @@ pub fn solve_part_2() -> Result<(), ()> {
     Ok(())
 }
 
-fn transform(subject_number: u64, loop_times: u64) -> u64 {
-    let mut value = 1;
-    for _ in 0..loop_times {
-        value *= subject_number;
-        value %= 20_201_227;
-    }
-    value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Handshake {
+    subject_number: u64,
+    modulus: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HandshakeResult {
+    card_loop_size: u64,
+    door_loop_size: u64,
+    encryption_key: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Input {
+    handshake: Handshake,
+    card_key: u64,
+    door_key: u64,
 }
 
-fn transform_with_target(subject_number: u64, target: u64) -> u64 {
-    let mut value = 1;
-    for i in 0.. {
-        if i % 1_000_000 == 0 {
-            print!("X");
+impl Handshake {
+    const DEFAULT: Handshake = Handshake {
+        subject_number: 7,
+        modulus: 20_201_227,
+    };
+
+    fn new(subject_number: u64, modulus: u64) -> Result<Handshake> {
+        if modulus < 2 {
+            return Err(anyhow!("The modulus must be at least 2, not {}", modulus));
+        }
+        if num::integer::gcd(subject_number, modulus) != 1 {
+            return Err(anyhow!(
+                "Subject number {} shares a factor with the modulus {}, so it can't be undone",
+                subject_number,
+                modulus
+            ));
+        }
+        Ok(Handshake {
+            subject_number,
+            modulus,
+        })
+    }
+
+    fn transform(&self, subject_number: u64, loop_size: u64) -> u64 {
+        pow_mod(subject_number, loop_size, self.modulus)
+    }
+
+    fn public_key(&self, loop_size: u64) -> u64 {
+        self.transform(self.subject_number, loop_size)
+    }
+
+    // The smallest loop size that turns the subject number into `public_key`.
+    fn loop_size(&self, public_key: u64) -> Result<u64> {
+        if public_key >= self.modulus {
+            return Err(anyhow!(
+                "Public key {} isn't below the modulus {}",
+                public_key,
+                self.modulus
+            ));
         }
-        if value == target {
-            return i;
+        let loop_size = discrete_log(self.subject_number, public_key, self.modulus).ok_or_else(|| {
+            let reachable = order(self.subject_number, self.modulus, &factorize(totient(self.modulus)));
+            anyhow!(
+                "Public key {} can't be reached from subject number {}, which only reaches {} of the {} keys modulo {}",
+                public_key,
+                self.subject_number,
+                reachable,
+                totient(self.modulus),
+                self.modulus
+            )
+        })?;
+        if self.public_key(loop_size) != public_key {
+            return Err(anyhow!(
+                "Loop size {} doesn't give back public key {}",
+                loop_size,
+                public_key
+            ));
         }
-        value *= subject_number;
-        value %= 20_201_227;
+        Ok(loop_size)
+    }
+
+    // Finds both loop sizes and checks that each side ends up with the same encryption key.
+    fn perform(&self, card_key: u64, door_key: u64) -> Result<HandshakeResult> {
+        let card_loop_size = self.loop_size(card_key).map_err(|e| anyhow!("Card: {}", e))?;
+        let door_loop_size = self.loop_size(door_key).map_err(|e| anyhow!("Door: {}", e))?;
+        let card_encryption_key = self.transform(door_key, card_loop_size);
+        let door_encryption_key = self.transform(card_key, door_loop_size);
+        if card_encryption_key != door_encryption_key {
+            return Err(anyhow!(
+                "The card's encryption key {} doesn't match the door's {}",
+                card_encryption_key,
+                door_encryption_key
+            ));
+        }
+        Ok(HandshakeResult {
+            card_loop_size,
+            door_loop_size,
+            encryption_key: card_encryption_key,
+        })
+    }
+}
+
+// The card's public key and then the door's, one per line, optionally preceded by
+// "subject: <number>" and "modulus: <number>" lines to change those from the puzzle's.
+fn parse_input(input: &str) -> Result<Input> {
+    let (mut subject_number, mut modulus) = (Handshake::DEFAULT.subject_number, Handshake::DEFAULT.modulus);
+    let mut keys = vec![];
+    for line in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let parse = |number: &str| {
+            number
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| anyhow!("Invalid number in line: {}", line))
+        };
+        if let Some(number) = line.strip_prefix("subject:") {
+            subject_number = parse(number)?;
+        } else if let Some(number) = line.strip_prefix("modulus:") {
+            modulus = parse(number)?;
+        } else {
+            keys.push(parse(line)?);
+        }
+    }
+    match keys[..] {
+        [card_key, door_key] => Ok(Input {
+            handshake: Handshake::new(subject_number, modulus)?,
+            card_key,
+            door_key,
+        }),
+        _ => Err(anyhow!("Expected two public keys, found {}", keys.len())),
     }
-    unreachable!();
 }
 
 #[cfg(test)]
@@ -44,12 +158,53 @@ mod tests {
 
     #[test]
     fn test_transform() {
-        assert_eq!(transform(7, 8), 5_764_801);
+        assert_eq!(Handshake::DEFAULT.public_key(8), 5_764_801);
+        assert_eq!(Handshake::DEFAULT.transform(17_807_724, 8), 14_897_079);
     }
 
     #[test]
     fn test_determine_loop_times() {
-        assert_eq!(transform_with_target(7, 5_764_801), 8);
-        assert_eq!(transform_with_target(7, 17_807_724), 11);
+        assert_eq!(Handshake::DEFAULT.loop_size(5_764_801).unwrap(), 8);
+        assert_eq!(Handshake::DEFAULT.loop_size(17_807_724).unwrap(), 11);
+        let input = parse_input("5764801\n17807724\n").unwrap();
+        assert_eq!(
+            part_1(&input).unwrap(),
+            HandshakeResult {
+                card_loop_size: 8,
+                door_loop_size: 11,
+                encryption_key: 14_897_079,
+            }
+        );
+    }
+
+    #[test]
+    fn test_other_handshakes() {
+        let input = parse_input("subject: 5\nmodulus: 1000000007\n\n123456789\n987654321").unwrap();
+        assert_eq!(input.handshake, Handshake::new(5, 1_000_000_007).unwrap());
+        let result = part_1(&input).unwrap();
+        assert_eq!(input.handshake.public_key(result.card_loop_size), 123_456_789);
+        assert_eq!(input.handshake.public_key(result.door_loop_size), 987_654_321);
+        assert_eq!(
+            result.encryption_key,
+            input.handshake.transform(123_456_789, result.door_loop_size)
+        );
+
+        // 2 isn't a generator mod 7: it only reaches 1, 2 and 4.
+        let handshake = Handshake::new(2, 7).unwrap();
+        assert_eq!(handshake.perform(4, 2).unwrap().encryption_key, 4);
+        assert_eq!(
+            handshake.perform(4, 3).unwrap_err().to_string(),
+            "Door: Public key 3 can't be reached from subject number 2, which only reaches 3 of the 6 keys modulo 7"
+        );
+        assert!(handshake.perform(9, 2).is_err());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(parse_input("5764801\n").is_err());
+        assert!(parse_input("5764801\n17807724\n1\n").is_err());
+        assert!(parse_input("subject: x\n5764801\n17807724\n").is_err());
+        assert!(parse_input("modulus: 1\n0\n0\n").is_err());
+        assert!(parse_input("subject: 6\nmodulus: 9\n1\n1\n").is_err());
     }
 }
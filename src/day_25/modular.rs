@@ -0,0 +1,214 @@
+use hashbrown::HashMap;
+use num::Integer;
+
+// Products are taken in 128 bits, so any `u64` modulus works, but baby-step giant-step keeps a table
+// of about the square root of the largest prime factor of the group order, and factorizing is by
+// trial division, so moduli much past 2^40 get slow.
+
+pub fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    (a as u128 * b as u128 % modulus as u128) as u64
+}
+
+pub fn pow_mod(base: u64, mut exponent: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    let mut base = base % modulus;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
+    }
+    result
+}
+
+pub fn inverse_mod(value: u64, modulus: u64) -> Option<u64> {
+    let gcd = (value as i128).extended_gcd(&(modulus as i128));
+    if gcd.gcd != 1 {
+        return None;
+    }
+    Some(gcd.x.rem_euclid(modulus as i128) as u64)
+}
+
+// The prime factors of `n` with their multiplicities, smallest first.
+pub fn factorize(mut n: u64) -> Vec<(u64, u32)> {
+    let mut factors = vec![];
+    let mut factor = 2;
+    while factor <= n / factor {
+        let mut count = 0;
+        while Integer::is_multiple_of(&n, &factor) {
+            n /= factor;
+            count += 1;
+        }
+        if count > 0 {
+            factors.push((factor, count));
+        }
+        factor += if factor == 2 { 1 } else { 2 };
+    }
+    if n > 1 {
+        factors.push((n, 1));
+    }
+    factors
+}
+
+pub fn totient(modulus: u64) -> u64 {
+    factorize(modulus)
+        .iter()
+        .fold(modulus, |phi, &(prime, _)| phi / prime * (prime - 1))
+}
+
+// The smallest `k > 0` with `base^k == 1`, given the factorization of a multiple of it.
+pub fn order(base: u64, modulus: u64, multiple_factors: &[(u64, u32)]) -> u64 {
+    let mut order: u64 = multiple_factors
+        .iter()
+        .map(|&(prime, count)| prime.pow(count))
+        .product();
+    for &(prime, count) in multiple_factors {
+        for _ in 0..count {
+            if pow_mod(base, order / prime, modulus) == 1 {
+                order /= prime;
+            } else {
+                break;
+            }
+        }
+    }
+    order
+}
+
+// The smallest `x < bound` with `base^x == target`, by baby-step giant-step. `base` has to be
+// invertible.
+pub fn baby_step_giant_step(base: u64, target: u64, modulus: u64, bound: u64) -> Option<u64> {
+    let target = target % modulus;
+    let step = (bound as f64).sqrt().ceil().max(1.0) as u64;
+    let mut baby_steps = HashMap::with_capacity(step as usize);
+    let mut value = 1 % modulus;
+    for j in 0..step {
+        baby_steps.entry(value).or_insert(j);
+        value = mul_mod(value, base, modulus);
+    }
+    let giant_step = pow_mod(inverse_mod(base, modulus)?, step, modulus);
+    let mut gamma = target;
+    for i in 0..step {
+        if let Some(&j) = baby_steps.get(&gamma) {
+            let x = i * step + j;
+            return if x < bound { Some(x) } else { None };
+        }
+        gamma = mul_mod(gamma, giant_step, modulus);
+    }
+    None
+}
+
+// The smallest `x` with `base^x == target`, by Pohlig–Hellman over the order of `base`: the log is
+// found a digit at a time modulo each prime power of the order, each digit by baby-step giant-step
+// in a subgroup the size of that prime, and the pieces joined with the Chinese remainder theorem.
+pub fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    let (base, target) = (base % modulus, target % modulus);
+    if modulus == 1 {
+        return Some(0);
+    }
+    inverse_mod(base, modulus)?;
+    let order = order(base, modulus, &factorize(totient(modulus)));
+    let mut residues = vec![];
+    for (prime, count) in factorize(order) {
+        let prime_power = prime.pow(count);
+        let generator = pow_mod(base, order / prime, modulus);
+        let mut x = 0;
+        let mut digit_value = 1;
+        for k in 1..=count {
+            // Strip off the digits found so far and look at what's left in the subgroup of `prime`.
+            let remaining = mul_mod(target, inverse_mod(pow_mod(base, x, modulus), modulus)?, modulus);
+            let in_subgroup = pow_mod(remaining, order / prime.pow(k), modulus);
+            let digit = baby_step_giant_step(generator, in_subgroup, modulus, prime)?;
+            x += digit * digit_value;
+            digit_value *= prime;
+        }
+        residues.push((x, prime_power));
+    }
+    let x = combine_residues(&residues);
+    if pow_mod(base, x, modulus) == target {
+        Some(x)
+    } else {
+        None
+    }
+}
+
+// The `x` below the product of the (coprime) moduli that leaves each of the given residues.
+fn combine_residues(residues: &[(u64, u64)]) -> u64 {
+    let (x, _) = residues.iter().fold((0, 1), |(x, product), &(residue, modulus)| {
+        let difference = (residue + modulus - x % modulus) % modulus;
+        let inverse = inverse_mod(product % modulus, modulus).expect("Moduli should be coprime");
+        let t = mul_mod(difference, inverse, modulus);
+        (x + product * t, product * modulus)
+    });
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+        let mut value = 1 % modulus;
+        for x in 0..modulus {
+            if value == target % modulus {
+                return Some(x);
+            }
+            value = mul_mod(value, base, modulus);
+        }
+        None
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(pow_mod(7, 11, 20_201_227), 17_807_724);
+        assert_eq!(pow_mod(5, 0, 1), 0);
+        assert_eq!(pow_mod(u64::MAX - 1, 3, u64::MAX), u64::MAX - 1);
+        assert_eq!(inverse_mod(3, 7), Some(5));
+        assert_eq!(inverse_mod(4, 8), None);
+        assert_eq!(factorize(20_201_226), vec![(2, 1), (3, 1), (29, 1), (116_099, 1)]);
+        assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(totient(20_201_227), 20_201_226);
+        assert_eq!(totient(36), 12);
+        assert_eq!(order(7, 20_201_227, &factorize(20_201_226)), 20_201_226);
+        assert_eq!(order(2, 7, &factorize(6)), 3);
+        assert_eq!(combine_residues(&[(2, 3), (3, 5), (2, 7)]), 23);
+    }
+
+    #[test]
+    fn test_logs_match_a_linear_search() {
+        for &modulus in &[2, 9, 11, 49, 97, 100, 101, 1024, 1009, 3 * 7 * 11] {
+            for base in 1..modulus.min(30) {
+                for target in 0..modulus.min(60) {
+                    let expected = if inverse_mod(base, modulus).is_some() {
+                        linear_log(base, target, modulus)
+                    } else {
+                        None
+                    };
+                    assert_eq!(
+                        discrete_log(base, target, modulus),
+                        expected,
+                        "{}^x = {} mod {}",
+                        base,
+                        target,
+                        modulus
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_logs() {
+        // Primes whose group orders have a large prime factor, and one whose order is smooth.
+        for &(modulus, base) in &[(1_000_000_007, 5), (20_201_227, 7), (998_244_353, 3)] {
+            for &x in &[0, 1, 12_345, 987_654_321 % (modulus - 1), modulus - 2] {
+                let target = pow_mod(base, x, modulus);
+                assert_eq!(discrete_log(base, target, modulus), Some(x));
+                assert_eq!(baby_step_giant_step(base, target, modulus, modulus - 1), Some(x));
+            }
+        }
+        // 2 only generates the quadratic residues mod 7, which 3 isn't one of.
+        assert_eq!(discrete_log(2, 3, 7), None);
+        assert_eq!(discrete_log(2, 4, 7), Some(2));
+    }
+}
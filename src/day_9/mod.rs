@@ -18,10 +18,10 @@ pub fn solve_part_2() -> Result<(), ()> {
     Ok(())
 }
 
-use crate::day_1::combinations_summing_to_n;
+use crate::day_1::k_sum::k_sum;
 fn part_1(items: &[usize], window_size: usize) -> usize {
     for win in items.windows(window_size + 1) {
-        if combinations_summing_to_n(&win[0..window_size], 2, win[window_size]).is_none() {
+        if k_sum(&win[0..window_size], 2, win[window_size]).is_empty() {
             return win[window_size];
         }
     }
@@ -23,6 +23,7 @@ mod day_6;
 mod day_7;
 mod day_8;
 mod day_9;
+mod matching;
 
 use argh::FromArgs;
 
@@ -36,6 +37,46 @@ struct Args {
     #[argh(positional)]
     /// part of the puzzle to do.
     part: usize,
+
+    #[argh(switch)]
+    /// print a log of how the answer was reached, for the days that keep one.
+    log: bool,
+
+    #[argh(option)]
+    /// number to report the first turn of in day 15's run statistics.
+    spoken: Option<u32>,
+
+    #[argh(option)]
+    /// file to write day 15's run statistics to, as CSV.
+    stats_csv: Option<String>,
+
+    #[argh(option)]
+    /// directory to write day 16's decoded tickets to, as tickets.csv and tickets.json.
+    tickets: Option<String>,
+
+    #[argh(option)]
+    /// changes to day 22's rules for the part, e.g. "recurse=2,ties=discard,return=descending".
+    rules: Option<String>,
+
+    #[argh(option)]
+    /// policy day 2's passwords have to pass as well as the part's, e.g. "count a 1-3; xor b 1 3; forbid xyz".
+    policy: Option<String>,
+
+    #[argh(option)]
+    /// file to read day 20's tile layout from, as saved by --layout-out, instead of solving it.
+    layout_in: Option<String>,
+
+    #[argh(option)]
+    /// file to save day 20's tile layout to.
+    layout_out: Option<String>,
+
+    #[argh(option)]
+    /// directory to write day 24's floor to, as one SVG frame per day.
+    frames: Option<String>,
+
+    #[argh(switch)]
+    /// label each tile of day 24's SVG frames with its coordinates.
+    coordinates: bool,
 }
 use anyhow::Result;
 fn main() -> Result<()> {
@@ -44,6 +85,9 @@ fn main() -> Result<()> {
     match (args.day, args.part) {
         (1, 1) => day_1::solve_part_1().expect(FAILURE_TEXT),
         (1, 2) => day_1::solve_part_2().expect(FAILURE_TEXT),
+        (2, part) if args.policy.is_some() => {
+            day_2::solve_with_policy(part, args.policy.as_deref().unwrap(), args.log).expect(FAILURE_TEXT)
+        }
         (2, 1) => day_2::solve_part_1().expect(FAILURE_TEXT),
         (2, 2) => day_2::solve_part_2().expect(FAILURE_TEXT),
         (3, 1) => day_3::solve_part_1().expect(FAILURE_TEXT),
@@ -70,9 +114,15 @@ fn main() -> Result<()> {
         (13, 2) => day_13::solve_part_2().expect(FAILURE_TEXT),
         (14, 1) => day_14::solve_part_1().expect(FAILURE_TEXT),
         (14, 2) => day_14::solve_part_2().expect(FAILURE_TEXT),
+        (15, part) if args.log || args.spoken.is_some() || args.stats_csv.is_some() => {
+            day_15::solve_with_stats(part, args.spoken, args.stats_csv.as_deref()).expect(FAILURE_TEXT)
+        }
         (15, 1) => day_15::solve_part_1().expect(FAILURE_TEXT),
         (15, 2) => day_15::solve_part_2().expect(FAILURE_TEXT),
         (16, 1) => day_16::solve_part_1().expect(FAILURE_TEXT),
+        (16, 2) if args.tickets.is_some() => {
+            day_16::solve_part_2_with_export(args.tickets.as_deref().unwrap()).expect(FAILURE_TEXT)
+        }
         (16, 2) => day_16::solve_part_2().expect(FAILURE_TEXT),
         (17, 1) => unimplemented!("This day no work yet, brah."),
         (17, 2) => day_17::solve_part_2().expect(FAILURE_TEXT),
@@ -80,15 +130,25 @@ fn main() -> Result<()> {
         (18, 2) => day_18::solve_part_2().expect(FAILURE_TEXT),
         (19, 1) => day_19::solve_part_1().expect(FAILURE_TEXT),
         (19, 2) => day_19::solve_part_2().expect(FAILURE_TEXT),
+        (20, part) if args.layout_in.is_some() || args.layout_out.is_some() => {
+            day_20::solve_with_layout(part, args.layout_in.as_deref(), args.layout_out.as_deref()).expect(FAILURE_TEXT)
+        }
         (20, 1) => day_20::solve_part_1().expect(FAILURE_TEXT),
         (20, 2) => day_20::solve_part_2().expect(FAILURE_TEXT),
         (21, 1) => day_21::solve_part_1().expect(FAILURE_TEXT),
         (21, 2) => day_21::solve_part_2().expect(FAILURE_TEXT),
+        (22, part) if args.rules.is_some() => {
+            day_22::solve_with_rules(part, args.rules.as_deref().unwrap(), args.log).expect(FAILURE_TEXT)
+        }
         (22, 1) => day_22::solve_part_1().expect(FAILURE_TEXT),
+        (22, 2) if args.log => day_22::solve_part_2_with_log().expect(FAILURE_TEXT),
         (22, 2) => day_22::solve_part_2().expect(FAILURE_TEXT),
         (23, 1) => day_23::solve_part_1().expect(FAILURE_TEXT),
         (23, 2) => day_23::solve_part_2().expect(FAILURE_TEXT),
         (24, 1) => day_24::solve_part_1().expect(FAILURE_TEXT),
+        (24, 2) if args.frames.is_some() => {
+            day_24::solve_part_2_with_frames(args.frames.as_deref().unwrap(), args.coordinates).expect(FAILURE_TEXT)
+        }
         (24, 2) => day_24::solve_part_2().expect(FAILURE_TEXT),
         (25, 1) => day_25::solve_part_1().expect(FAILURE_TEXT),
         (25, 2) => day_25::solve_part_2().expect(FAILURE_TEXT),
@@ -0,0 +1,355 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+pub type Candidates<L, R> = BTreeMap<L, BTreeSet<R>>;
+
+// The most assignments listed when there are several, since loosely constrained candidates can
+// allow far too many to enumerate.
+pub const MAX_ASSIGNMENTS: usize = 1000;
+
+// Assigns every left item exactly one of its candidate right items, with no right item used twice.
+pub struct Matcher<L, R> {
+    candidates: Candidates<L, R>,
+}
+
+// A pair that had to be matched because `left` had a single candidate left, once the
+// earlier forced pairs in `ruled_out` had taken its other candidates.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Forced<L, R> {
+    pub left: L,
+    pub right: R,
+    pub ruled_out: Vec<(L, R)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome<L, R> {
+    Unique(BTreeMap<L, R>),
+    // The first `MAX_ASSIGNMENTS` assignments found, at most.
+    Multiple(Vec<BTreeMap<L, R>>),
+    // A set of left items that have fewer candidates between them than there are items.
+    Impossible(Vec<L>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Report<L, R> {
+    pub forced: Vec<Forced<L, R>>,
+    pub outcome: Outcome<L, R>,
+}
+
+impl<L: Ord + Clone, R: Ord + Clone> Matcher<L, R> {
+    pub fn new(candidates: Candidates<L, R>) -> Matcher<L, R> {
+        Matcher { candidates }
+    }
+
+    pub fn solve(&self) -> Report<L, R> {
+        let (forced, remaining) = self.eliminate();
+        let outcome = match self.hall_violator() {
+            Some(violator) => Outcome::Impossible(violator),
+            None => {
+                let mut current = forced.iter().map(|f| (f.left.clone(), f.right.clone())).collect();
+                let mut used = forced.iter().map(|f| f.right.clone()).collect();
+                let remaining: Vec<_> = remaining.iter().collect();
+                let mut assignments = vec![];
+                extend_assignment(&remaining, &mut current, &mut used, &mut assignments);
+                if assignments.len() == 1 {
+                    Outcome::Unique(assignments.pop().unwrap())
+                } else {
+                    Outcome::Multiple(assignments)
+                }
+            }
+        };
+        Report { forced, outcome }
+    }
+
+    // The candidates that appear in at least one assignment, found by checking that the rest can
+    // still all be matched with each pair fixed, rather than by listing every assignment.
+    pub fn possible_pairs(&self) -> Candidates<L, R> {
+        self.candidates
+            .iter()
+            .map(|(left, rights)| {
+                let possible = rights
+                    .iter()
+                    .filter(|&right| {
+                        let mut fixed = self.candidates.clone();
+                        for (other, others) in fixed.iter_mut() {
+                            if other != left {
+                                others.remove(right);
+                            }
+                        }
+                        fixed.insert(left.clone(), vec![right.clone()].into_iter().collect());
+                        let (match_left, _) = Graph::new(&fixed).hopcroft_karp();
+                        match_left.iter().all(Option::is_some)
+                    })
+                    .cloned()
+                    .collect();
+                (left.clone(), possible)
+            })
+            .collect()
+    }
+
+    // Repeatedly matches any left item with a single candidate and removes that candidate from
+    // everyone else. Returns the forced pairs in order, and the candidates still undecided.
+    pub fn eliminate(&self) -> (Vec<Forced<L, R>>, Candidates<L, R>) {
+        let mut remaining = self.candidates.clone();
+        let mut ruled_out: BTreeMap<L, Vec<(L, R)>> = BTreeMap::new();
+        let mut forced = vec![];
+        while let Some(left) = remaining
+            .iter()
+            .find(|(_, rights)| rights.len() == 1)
+            .map(|(left, _)| left.clone())
+        {
+            let right = remaining.remove(&left).unwrap().into_iter().next().unwrap();
+            for (other, rights) in remaining.iter_mut() {
+                if rights.remove(&right) {
+                    ruled_out
+                        .entry(other.clone())
+                        .or_default()
+                        .push((left.clone(), right.clone()));
+                }
+            }
+            forced.push(Forced {
+                ruled_out: ruled_out.remove(&left).unwrap_or_default(),
+                left,
+                right,
+            });
+        }
+        (forced, remaining)
+    }
+
+    // Finds a maximum matching with Hopcroft–Karp. When it leaves a left item out, the left items
+    // reachable from it by alternating paths only have the matched partners of the others as candidates.
+    fn hall_violator(&self) -> Option<Vec<L>> {
+        let graph = Graph::new(&self.candidates);
+        let (match_left, match_right) = graph.hopcroft_karp();
+        let unmatched = match_left.iter().position(Option::is_none)?;
+        let mut visited = vec![false; graph.lefts.len()];
+        visited[unmatched] = true;
+        let mut queue = VecDeque::from(vec![unmatched]);
+        while let Some(l) = queue.pop_front() {
+            for &r in graph.adjacency[l].iter() {
+                let partner = match_right[r].expect("A maximum matching has no augmenting paths");
+                if !visited[partner] {
+                    visited[partner] = true;
+                    queue.push_back(partner);
+                }
+            }
+        }
+        Some(
+            visited
+                .iter()
+                .enumerate()
+                .filter(|(_, &v)| v)
+                .map(|(l, _)| graph.lefts[l].clone())
+                .collect(),
+        )
+    }
+}
+
+// Adds every way to give each remaining left item an unused candidate to `current`, stopping once
+// there are `MAX_ASSIGNMENTS` of them.
+fn extend_assignment<L: Ord + Clone, R: Ord + Clone>(
+    remaining: &[(&L, &BTreeSet<R>)],
+    current: &mut BTreeMap<L, R>,
+    used: &mut BTreeSet<R>,
+    assignments: &mut Vec<BTreeMap<L, R>>,
+) {
+    let ((left, rights), rest) = match remaining.split_first() {
+        Some(first) => first,
+        None => return assignments.push(current.clone()),
+    };
+    for right in rights.iter() {
+        if assignments.len() == MAX_ASSIGNMENTS {
+            return;
+        }
+        if used.insert(right.clone()) {
+            current.insert((*left).clone(), right.clone());
+            extend_assignment(rest, current, used, assignments);
+            current.remove(*left);
+            used.remove(right);
+        }
+    }
+}
+
+// The candidates with both sides replaced by indices.
+struct Graph<'a, L, R> {
+    lefts: Vec<&'a L>,
+    rights: Vec<&'a R>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl<'a, L: Ord, R: Ord> Graph<'a, L, R> {
+    fn new(candidates: &'a Candidates<L, R>) -> Graph<'a, L, R> {
+        let rights: Vec<&R> = candidates
+            .values()
+            .flatten()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let adjacency = candidates
+            .values()
+            .map(|set| set.iter().map(|r| rights.binary_search(&r).unwrap()).collect())
+            .collect();
+        Graph {
+            lefts: candidates.keys().collect(),
+            rights,
+            adjacency,
+        }
+    }
+
+    fn hopcroft_karp(&self) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let mut match_left = vec![None; self.lefts.len()];
+        let mut match_right = vec![None; self.rights.len()];
+        loop {
+            // Layer the left items by alternating distance from the unmatched ones.
+            let mut layer = vec![usize::MAX; self.lefts.len()];
+            let mut queue = VecDeque::new();
+            for (l, matched) in match_left.iter().enumerate() {
+                if matched.is_none() {
+                    layer[l] = 0;
+                    queue.push_back(l);
+                }
+            }
+            let mut found_augmenting_path = false;
+            while let Some(l) = queue.pop_front() {
+                for &r in self.adjacency[l].iter() {
+                    match match_right[r] {
+                        None => found_augmenting_path = true,
+                        Some(partner) if layer[partner] == usize::MAX => {
+                            layer[partner] = layer[l] + 1;
+                            queue.push_back(partner);
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+            if !found_augmenting_path {
+                return (match_left, match_right);
+            }
+            for l in 0..self.lefts.len() {
+                if match_left[l].is_none() {
+                    self.augment(l, &mut layer, &mut match_left, &mut match_right);
+                }
+            }
+        }
+    }
+
+    fn augment(
+        &self,
+        l: usize,
+        layer: &mut Vec<usize>,
+        match_left: &mut Vec<Option<usize>>,
+        match_right: &mut Vec<Option<usize>>,
+    ) -> bool {
+        for &r in self.adjacency[l].iter() {
+            let can_take = match match_right[r] {
+                None => true,
+                Some(partner) => {
+                    layer[partner] == layer[l] + 1 && self.augment(partner, layer, match_left, match_right)
+                }
+            };
+            if can_take {
+                match_left[l] = Some(r);
+                match_right[r] = Some(l);
+                return true;
+            }
+        }
+        // Nothing useful beyond here in this phase.
+        layer[l] = usize::MAX;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matcher(candidates: &[(u32, &[char])]) -> Matcher<u32, char> {
+        Matcher::new(
+            candidates
+                .iter()
+                .map(|(left, rights)| (*left, rights.iter().copied().collect()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_elimination_finds_unique_assignment() {
+        let report = matcher(&[(1, &['a', 'b', 'c']), (2, &['a']), (3, &['a', 'b'])]).solve();
+        let expected: BTreeMap<u32, char> = vec![(1, 'c'), (2, 'a'), (3, 'b')].into_iter().collect();
+        assert_eq!(report.outcome, Outcome::Unique(expected));
+        assert_eq!(
+            report.forced,
+            vec![
+                Forced {
+                    left: 2,
+                    right: 'a',
+                    ruled_out: vec![]
+                },
+                Forced {
+                    left: 3,
+                    right: 'b',
+                    ruled_out: vec![(2, 'a')]
+                },
+                Forced {
+                    left: 1,
+                    right: 'c',
+                    ruled_out: vec![(2, 'a'), (3, 'b')]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_multiple_assignments() {
+        let report = matcher(&[(1, &['a', 'b']), (2, &['a', 'b']), (3, &['c'])]).solve();
+        assert_eq!(report.forced.len(), 1);
+        match report.outcome {
+            Outcome::Multiple(assignments) => {
+                assert_eq!(assignments.len(), 2);
+                assert!(assignments.iter().all(|a| a[&3] == 'c' && a[&1] != a[&2]));
+            }
+            outcome => panic!("Expected several assignments, got {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn test_too_many_assignments() {
+        let all: &[char] = &['a', 'b', 'c', 'd', 'e', 'f', 'g', 'h'];
+        let candidates: Vec<(u32, &[char])> = (1..=8).map(|left| (left, all)).collect();
+        let matcher = matcher(&candidates);
+        match matcher.solve().outcome {
+            Outcome::Multiple(assignments) => assert_eq!(assignments.len(), MAX_ASSIGNMENTS),
+            outcome => panic!("Expected several assignments, got {:?}", outcome),
+        }
+        assert_eq!(matcher.possible_pairs(), matcher.candidates);
+    }
+
+    #[test]
+    fn test_possible_pairs() {
+        // 3 can't take 'b', or 1 and 2 would both need 'a'.
+        let possible = matcher(&[(1, &['a', 'b']), (2, &['a', 'b']), (3, &['b', 'c'])]).possible_pairs();
+        let expected = matcher(&[(1, &['a', 'b']), (2, &['a', 'b']), (3, &['c'])]).candidates;
+        assert_eq!(possible, expected);
+    }
+
+    #[test]
+    fn test_impossible_assignment() {
+        let report = matcher(&[(1, &['a', 'b']), (2, &['a', 'b']), (3, &['a', 'b']), (4, &['c', 'd'])]).solve();
+        assert_eq!(report.outcome, Outcome::Impossible(vec![1, 2, 3]));
+
+        let report = matcher(&[(1, &['a']), (2, &[])]).solve();
+        assert_eq!(report.outcome, Outcome::Impossible(vec![2]));
+    }
+
+    #[test]
+    fn test_hopcroft_karp() {
+        // Greedily taking the first candidate for each would give 1 'a' and leave 2 with nothing.
+        let candidates = matcher(&[(1, &['a', 'b']), (2, &['a'])]).candidates;
+        let (match_left, match_right) = Graph::new(&candidates).hopcroft_karp();
+        assert_eq!(match_left, vec![Some(1), Some(0)]);
+        assert_eq!(match_right, vec![Some(1), Some(0)]);
+
+        let candidates = matcher(&[(1, &['a', 'b']), (2, &['a', 'c']), (3, &['b'])]).candidates;
+        let (match_left, _) = Graph::new(&candidates).hopcroft_karp();
+        assert_eq!(match_left, vec![Some(0), Some(2), Some(1)]);
+    }
+}